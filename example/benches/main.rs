@@ -1,6 +1,14 @@
+use std::alloc::System;
+
+use haste::alloc::CountingAllocator;
+
 // Needed if we're using no items from example.
 extern crate example;
 
+// Reports the allocations of each benchmark.
+#[global_allocator]
+static ALLOC: CountingAllocator = CountingAllocator::new(System);
+
 fn main() {
     haste::main();
 }
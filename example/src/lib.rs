@@ -1,6 +1,6 @@
 use std::{hint::black_box, thread, time::Duration};
 
-use haste::{Haste, throughput::Throughput};
+use haste::{BatchSize, DropMode, Haste, SamplingMode, throughput::Throughput};
 use tokio::runtime::Runtime;
 
 fn fibonacci(n: u64) -> u64 {
@@ -25,6 +25,11 @@ fn bench_add(arg: u64) {
     add(arg, arg);
 }
 
+#[haste::bench(args = [1024, 1024 * 1024], drop_mode = DropMode::Inside)]
+fn alloc_vec(len: usize) -> Vec<u8> {
+    vec![1; len]
+}
+
 #[haste::bench]
 fn use_haste_directly(mut haste: Haste) {
     haste
@@ -34,8 +39,74 @@ fn use_haste_directly(mut haste: Haste) {
         });
 }
 
+#[haste::bench(args(len = [100, 1000], reversed = [false, true]))]
+fn sort(len: u64, reversed: bool) -> Vec<u64> {
+    let mut data: Vec<u64> = if reversed {
+        (0..len).rev().collect()
+    } else {
+        (0..len).collect()
+    };
+    data.sort();
+    data
+}
+
+#[haste::bench(consts = [16, 256], args = [1, 2])]
+fn fill_buffer<const N: usize>(arg: u8) -> [u8; N] {
+    [arg; N]
+}
+
+#[haste::bench(types = [u8, u32, u64], consts = [16])]
+fn sum<T: From<u8> + std::iter::Sum, const N: usize>() -> T {
+    (0..N).map(|i| T::from(i as u8)).sum()
+}
+
+fn reversed_data(len: u64) -> Vec<u64> {
+    (0..len).rev().collect()
+}
+
+#[haste::bench(args = [1000, 100_000], setup = reversed_data)]
+fn sum_data(data: &[u64]) -> u64 {
+    data.iter().sum()
+}
+
+#[haste::bench(args = [String::from("haste"), "haste".repeat(4)])]
+fn count_vowels(text: &str) -> usize {
+    text.chars().filter(|c| "aeiou".contains(*c)).count()
+}
+
+#[haste::bench(
+    args = [vec![1; 10], vec![1; 1000], vec![1; 100_000]],
+    arg_names = ["small", "medium", "large"]
+)]
+fn max(data: &[u32]) -> Option<u32> {
+    data.iter().copied().max()
+}
+
+// tuples don't implement `Display`, so their `Debug` output is used in the label
+#[haste::bench(args = [(3, 4), (300, 400)])]
+fn hypot((a, b): (u32, u32)) -> f64 {
+    f64::from(a).hypot(f64::from(b))
+}
+
+#[haste::bench]
+fn sort_unstable(mut haste: Haste) {
+    // The function is called again for every benchmark and to list them, so the data is only
+    // created in the setup, which is skipped unless the benchmark is run.
+    haste.bench_batched(
+        "sort_unstable",
+        || (0..1000).rev().collect::<Vec<u64>>(),
+        |mut data| {
+            data.sort_unstable();
+            data
+        },
+        BatchSize::SmallInput,
+    );
+}
+
 #[haste::bench]
 fn bench_async(mut haste: Haste) {
+    // Created on every call of the function, including the one which only lists the benchmark.
+    // An `async fn` benchmark only creates its runtime if it is run.
     let rt = Runtime::new().unwrap();
     haste.bench_async("bench async sleep", &rt, async || {
         tokio::time::sleep(Duration::from_millis(50)).await
@@ -47,7 +118,44 @@ async fn bench_async_overhead() {
     add(5, 5);
 }
 
-#[haste::bench(args = [1,2,3], throughput = Throughput::Bytes(arg as usize * 100))]
+#[haste::bench(
+    args = [1,2,3],
+    throughput = Throughput::Bytes(arg as usize * 100),
+    sample_time = Duration::from_secs(1),
+    sample_count = 20,
+    sampling_mode = SamplingMode::Flat,
+)]
 fn bench_throughput(arg: u64) {
     thread::sleep(Duration::from_millis(arg));
 }
+
+mod parsing {
+    // labeled `parsing::parse/..`
+    #[haste::bench(args = ["42", "-17"])]
+    fn parse(text: &str) -> i64 {
+        text.parse().unwrap()
+    }
+
+    // labeled `floats::parse_float/..`
+    #[haste::bench(args = ["4.2", "-1.7"], group = "floats")]
+    fn parse_float(text: &str) -> f64 {
+        text.parse().unwrap()
+    }
+}
+
+// only run with `--ignored` or `--include-ignored`
+#[haste::bench(args = [35], ignore)]
+fn bench_fib_slow(arg: u64) -> u64 {
+    fibonacci(arg)
+}
+
+// measures the CPU time of the benchmark thread, which excludes the time spent sleeping
+#[cfg(unix)]
+#[haste::bench(
+    timer = haste::timer::ThreadCpuTime,
+    sample_time = Duration::from_millis(500),
+    sample_count = 10
+)]
+fn sleep_cpu_time() {
+    thread::sleep(Duration::from_millis(1));
+}
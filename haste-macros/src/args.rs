@@ -1,63 +1,148 @@
-use syn::{Error, Expr, Ident, Token, bracketed, parse::Parse, punctuated::Punctuated};
+use syn::{
+    Error, Expr, Ident, LitStr, Token, Type, bracketed, parenthesized, parse::Parse,
+    punctuated::Punctuated,
+};
+
+/// The values of the `args` argument.
+pub(crate) enum BenchArgs {
+    /// `args = [..]` for a function with a single parameter.
+    Unnamed(Punctuated<Expr, Token![,]>),
+    /// `args(name = [..], ..)` with a list of values for each named parameter.
+    Named(Vec<(Ident, Punctuated<Expr, Token![,]>)>),
+}
 
 #[derive(Default)]
 pub(crate) struct Args {
-    pub(crate) args: Option<Punctuated<Expr, Token![,]>>,
+    pub(crate) args: Option<BenchArgs>,
+    /// `arg_names = [..]` with one label for each value of `args = [..]`.
+    pub(crate) arg_names: Option<(Ident, Punctuated<LitStr, Token![,]>)>,
     pub(crate) runtime: Option<Expr>,
     pub(crate) throughput: Option<Expr>,
+    pub(crate) warmup: Option<Expr>,
+    pub(crate) sample_time: Option<Expr>,
+    pub(crate) sample_count: Option<Expr>,
+    pub(crate) sampling_mode: Option<Expr>,
+    pub(crate) drop_mode: Option<Expr>,
+    pub(crate) timer: Option<Expr>,
+    pub(crate) consts: Option<Punctuated<Expr, Token![,]>>,
+    pub(crate) types: Option<Punctuated<Type, Token![,]>>,
+    pub(crate) setup: Option<Expr>,
+    pub(crate) group: Option<Expr>,
+    pub(crate) ignore: bool,
 }
 
 impl Parse for Args {
     fn parse(input: syn::parse::ParseStream) -> syn::Result<Self> {
-        if input.is_empty() {
-            return Ok(Args::default());
-        }
-        let mut args = None;
-        let mut runtime = None;
-        let mut throughput = None;
+        let mut args = Args::default();
 
         while !input.is_empty() {
             let ident: Ident = input.parse()?;
-            match ident.to_string().as_str() {
+            let expr_slot = match ident.to_string().as_str() {
                 "args" => {
-                    if args.is_some() {
+                    if args.args.is_some() {
                         return Err(Error::new_spanned(ident, "duplicate args"));
                     }
+                    args.args = Some(input.parse()?);
+                    None
+                }
+                "arg_names" => {
+                    if args.arg_names.is_some() {
+                        return Err(Error::new_spanned(ident, "duplicate arg_names"));
+                    }
                     input.parse::<Token![=]>()?;
                     let content;
                     bracketed!(content in input);
-                    args = Some(Punctuated::parse_terminated(&content)?);
+                    args.arg_names = Some((ident.clone(), Punctuated::parse_terminated(&content)?));
+                    None
                 }
-                "runtime" => {
-                    if runtime.is_some() {
-                        return Err(Error::new_spanned(ident, "duplicate runtime"));
+                "ignore" => {
+                    if args.ignore {
+                        return Err(Error::new_spanned(ident, "duplicate ignore"));
+                    }
+                    args.ignore = true;
+                    None
+                }
+                "consts" => {
+                    if args.consts.is_some() {
+                        return Err(Error::new_spanned(ident, "duplicate consts"));
                     }
                     input.parse::<Token![=]>()?;
-                    runtime = Some(input.parse()?);
+                    args.consts = Some(parse_values(input)?);
+                    None
                 }
-                "throughput" => {
-                    if throughput.is_some() {
-                        return Err(Error::new_spanned(ident, "duplicate throughput"));
+                "types" => {
+                    if args.types.is_some() {
+                        return Err(Error::new_spanned(ident, "duplicate types"));
                     }
                     input.parse::<Token![=]>()?;
-                    throughput = Some(input.parse()?);
+                    let content;
+                    bracketed!(content in input);
+                    args.types = Some(Punctuated::parse_terminated(&content)?);
+                    None
                 }
+                "runtime" => Some(&mut args.runtime),
+                "setup" => Some(&mut args.setup),
+                "group" => Some(&mut args.group),
+                "throughput" => Some(&mut args.throughput),
+                "warmup" => Some(&mut args.warmup),
+                "sample_time" => Some(&mut args.sample_time),
+                "sample_count" => Some(&mut args.sample_count),
+                "sampling_mode" => Some(&mut args.sampling_mode),
+                "drop_mode" => Some(&mut args.drop_mode),
+                "timer" => Some(&mut args.timer),
                 _ => {
                     return Err(Error::new_spanned(
                         &ident,
                         format!("unknown argument {ident}"),
                     ));
                 }
+            };
+            if let Some(slot) = expr_slot {
+                if slot.is_some() {
+                    return Err(Error::new_spanned(&ident, format!("duplicate {ident}")));
+                }
+                input.parse::<Token![=]>()?;
+                *slot = Some(input.parse()?);
             }
             if !input.is_empty() {
                 input.parse::<Token![,]>()?;
             }
         }
 
-        Ok(Args {
-            args,
-            runtime,
-            throughput,
-        })
+        Ok(args)
     }
 }
+
+impl Parse for BenchArgs {
+    fn parse(input: syn::parse::ParseStream) -> syn::Result<Self> {
+        if input.peek(Token![=]) {
+            input.parse::<Token![=]>()?;
+            return Ok(BenchArgs::Unnamed(parse_values(input)?));
+        }
+
+        let content;
+        parenthesized!(content in input);
+        let mut named: Vec<(Ident, _)> = Vec::new();
+        while !content.is_empty() {
+            let name: Ident = content.parse()?;
+            if named.iter().any(|(existing, _)| *existing == name) {
+                return Err(Error::new_spanned(
+                    &name,
+                    format!("duplicate args for {name}"),
+                ));
+            }
+            content.parse::<Token![=]>()?;
+            named.push((name, parse_values(&content)?));
+            if !content.is_empty() {
+                content.parse::<Token![,]>()?;
+            }
+        }
+        Ok(BenchArgs::Named(named))
+    }
+}
+
+fn parse_values(input: syn::parse::ParseStream) -> syn::Result<Punctuated<Expr, Token![,]>> {
+    let content;
+    bracketed!(content in input);
+    Punctuated::parse_terminated(&content)
+}
@@ -1,8 +1,11 @@
 use proc_macro2::TokenStream;
-use quote::{ToTokens, quote};
-use syn::{Error, ItemFn, ItemMod, Stmt, parse::Parse};
+use quote::{ToTokens, format_ident, quote};
+use syn::{
+    Error, Expr, FnArg, GenericParam, Ident, ItemFn, ItemMod, LitStr, Pat, Stmt, Token, Type,
+    parse::Parse, punctuated::Punctuated,
+};
 
-use crate::Args;
+use crate::{Args, args::BenchArgs};
 
 pub struct BenchFunc {
     func: ItemFn,
@@ -17,18 +20,98 @@ impl Parse for BenchFunc {
     }
 }
 
+/// A parameter of the benchmarked function and the values it is benchmarked with.
+struct ArgDim {
+    /// The variable the current value is bound to in the generated code.
+    binding: Ident,
+    values: Punctuated<Expr, Token![,]>,
+    /// Labels for the values, used instead of formatting the values themselves.
+    names: Option<Punctuated<LitStr, Token![,]>>,
+    /// Whether the label part is prefixed with the parameter name, e.g. `len=1000`.
+    named: bool,
+    /// Whether the parameter is a reference and the value is passed by reference.
+    by_ref: bool,
+}
+
 pub(crate) fn expand(args: Args, bench_func: BenchFunc) -> TokenStream {
     let Args {
         args,
+        arg_names,
         runtime,
         throughput,
+        warmup,
+        sample_time,
+        sample_count,
+        sampling_mode,
+        drop_mode,
+        timer,
+        consts,
+        types,
+        setup,
+        group,
+        ignore,
     } = args;
     let BenchFunc { mut func } = bench_func;
 
-    if args.is_some() && func.sig.inputs.len() != 1 {
+    let dims = match args {
+        Some(BenchArgs::Named(_)) | None if arg_names.is_some() => {
+            let (ident, _) = arg_names.expect("checked by guard");
+            return Error::new_spanned(ident, "arg_names requires args = [..]")
+                .into_compile_error();
+        }
+        None if setup.is_some() => {
+            return Error::new_spanned(setup, "setup requires args = [..]").into_compile_error();
+        }
+        None => None,
+        Some(BenchArgs::Unnamed(values)) => {
+            if func.sig.inputs.len() != 1 {
+                return Error::new_spanned(
+                    func,
+                    "When args are supplied, the benchmarked function must have exactly one parameter.",
+                )
+                .into_compile_error();
+            }
+            // with a setup function, the benchmarked function takes a reference to the input
+            // created from the arg, which is handled separately
+            let by_ref = setup.is_none() && is_reference_param(&func.sig.inputs[0]);
+            let names = match arg_names {
+                Some((_, names)) if names.len() != values.len() => {
+                    return Error::new_spanned(
+                        names,
+                        format!(
+                            "arg_names must have one name per arg, expected {} names",
+                            values.len()
+                        ),
+                    )
+                    .into_compile_error();
+                }
+                Some((_, names)) => Some(names),
+                None => None,
+            };
+            Some(vec![ArgDim {
+                binding: format_ident!("arg"),
+                values,
+                names,
+                named: false,
+                by_ref,
+            }])
+        }
+        Some(BenchArgs::Named(_)) if setup.is_some() => {
+            return Error::new_spanned(setup, "setup can only be used with args = [..]")
+                .into_compile_error();
+        }
+        Some(BenchArgs::Named(named)) => match named_arg_dims(&func, named) {
+            Ok(dims) => Some(dims),
+            Err(err) => return err.into_compile_error(),
+        },
+    };
+    if let Some(group) = &group
+        && dims.is_none()
+        && func.sig.inputs.len() == 1
+    {
         return Error::new_spanned(
-            func,
-            "When args are supplied, the benchmarked function must have exactly one parameter.",
+            group,
+            "group can't be used for functions taking `Haste`, as they choose their own labels",
         )
         .into_compile_error();
     }
@@ -36,93 +119,413 @@ pub(crate) fn expand(args: Args, bench_func: BenchFunc) -> TokenStream {
     let ident_str = ident.to_string();
     let is_async = func.sig.asyncness.is_some();
 
+    // the runtime is only created once the first benchmark of the function is run
     let mut runtime_tokens = None;
+    let mut force_runtime = None;
     if is_async {
-        runtime_tokens = Some(match runtime {
-            Some(rt_expr) => quote! {
-                let rt = #rt_expr;
-            },
-            None => {
-                quote! {
-                    let rt = ::haste::__private::tokio::runtime::Runtime::new().unwrap();
-                }
+        let rt_expr = match runtime {
+            Some(rt_expr) => quote! { #rt_expr },
+            None => quote! { ::haste::__private::tokio::runtime::Runtime::new().unwrap() },
+        };
+        runtime_tokens = Some(quote! {
+            let rt = ::std::cell::LazyCell::new(|| #rt_expr);
+        });
+        force_runtime = Some(quote! {
+            let rt = &*rt;
+        });
+    }
+
+    let config_calls: TokenStream = [
+        (quote! {with_warmup}, warmup),
+        (quote! {with_sample_time}, sample_time),
+        (quote! {with_sample_count}, sample_count),
+        (quote! {with_sampling_mode}, sampling_mode),
+        (quote! {with_drop_mode}, drop_mode),
+        (quote! {with_timer}, timer),
+    ]
+    .into_iter()
+    .filter_map(|(method, expr)| {
+        expr.map(|expr| {
+            quote! {
+                b.#method(#expr);
             }
         })
-    }
+    })
+    .collect();
 
-    let mut bench_call = quote! {b};
-    if let Some(throughput) = throughput {
-        bench_call = quote! {
-            #bench_call.with_throughput(#throughput)
-        };
-    }
-    let bench_arg = args.as_ref().map(|_| {
-        quote! {
-            ::std::hint::black_box(arg)
+    let instances = match generic_instances(&func, consts, types) {
+        Ok(instances) => instances,
+        Err(err) => return err.into_compile_error(),
+    };
+
+    let bench_args = dims.iter().flatten().map(|dim| {
+        let binding = &dim.binding;
+        if dim.by_ref {
+            quote! {
+                ::std::hint::black_box(&#binding)
+            }
+        } else {
+            quote! {
+                ::std::hint::black_box(#binding)
+            }
         }
     });
+    let bench_arg = quote! { #(#bench_args),* };
 
-    if is_async {
-        bench_call = quote! {
-            #bench_call.bench_async(label, &rt, || {
-                super::#ident(#bench_arg)
-            })
+    let mut bench_fns = Vec::with_capacity(instances.len());
+    for (idx, instance) in instances.iter().enumerate() {
+        let generic_args = &instance.generic_args;
+        let func_path = quote! { super::#ident #generic_args };
+        let instance_parts = &instance.label_parts;
+        let group_call = group.as_ref().map(|group| quote! { .with_group(#group) });
+        let label_root = quote! {
+            ::haste::Label::new(#ident_str)
+                .with_module_path(::haste::__private::bench_module_path(::std::module_path!()))
+                #group_call
+                #(.with_part(#instance_parts))*
         };
-    } else {
-        bench_call = quote! {
-            #bench_call.bench(label, || {
-                super::#ident(#bench_arg)
-            });
-        }
-    }
 
-    let bench_body = match (func.sig.inputs.len(), args) {
-        (0, None) => Some(quote! {
-            #runtime_tokens
-            let label = ::haste::Label::new(#ident_str);
-            #bench_call
-        }),
-        (1, None) => None,
-        (1, Some(args)) => Some(quote! {
-            #runtime_tokens
-            for arg in [#args] {
-                let label = ::haste::Label::new(#ident_str).with_part(&arg);
-                #bench_call
+        let mut bench_call = quote! {b};
+        if let Some(throughput) = &throughput {
+            bench_call = quote! {
+                #bench_call.with_throughput(#throughput)
+            };
+        }
+        if let Some(setup) = &setup {
+            let mut call = quote! {
+                #func_path(::std::hint::black_box(input))
+            };
+            if is_async {
+                call = quote! {
+                    rt.block_on(#call)
+                };
+            }
+            bench_call = quote! {
+                #bench_call.bench_with_input(label, || (#setup)(arg), |input| #call);
+            };
+        } else if is_async {
+            bench_call = quote! {
+                #bench_call.bench_async(label, rt, || {
+                    #func_path(#bench_arg)
+                })
+            };
+        } else {
+            bench_call = quote! {
+                #bench_call.bench(label, || {
+                    #func_path(#bench_arg)
+                });
             }
-        }),
-        (count, _) => {
-            return Error::new_spanned(func, format!("Unsupported number of parameters: {count}"))
-                .into_compile_error();
         }
-    };
-
-    let bench_module = bench_body.map(|body| {
-        quote! {
-            #[doc(hidden)]
-            mod __haste_bench {
-                use super::*;
 
-                #[::haste::__private::distributed_slice(haste::__private::BENCHMARKS)]
-                #[linkme(crate = haste::__private::linkme)]
-                fn #ident(mut b: ::haste::Haste) {
+        let bench_body = match (func.sig.inputs.len(), &dims) {
+            (0, None) => quote! {
+                #config_calls
+                #runtime_tokens
+                let label = #label_root;
+                if ::haste::__private::should_run(&mut b, &label) {
+                    #force_runtime
+                    #bench_call
+                }
+            },
+            // the function takes `Haste` directly and chooses its own labels
+            (1, None) => {
+                let throughput_call = throughput.as_ref().map(|throughput| {
+                    quote! {
+                        b.with_throughput(#throughput);
+                    }
+                });
+                quote! {
+                    #config_calls
+                    #throughput_call
+                    #func_path(b)
+                }
+            }
+            (_, Some(dims)) => {
+                let label_parts = dims.iter().map(|dim| {
+                    let binding = &dim.binding;
+                    let part = match &dim.names {
+                        Some(names) => {
+                            let idx = format_ident!("__{binding}_idx");
+                            let names = names.iter();
+                            quote! { [#(#names),*][#idx] }
+                        }
+                        // falls back to `Debug` for args which don't implement `Display`
+                        None => quote! {
+                            (&::haste::__private::LabelPart(&#binding)).label_part()
+                        },
+                    };
+                    if dim.named {
+                        let fmt = format!("{binding}={{}}");
+                        quote! { .with_part(::std::format!(#fmt, #part)) }
+                    } else {
+                        quote! { .with_part(#part) }
+                    }
+                });
+                let value_bindings = dims
+                    .iter()
+                    .map(|dim| {
+                        let binding = &dim.binding;
+                        let idx = format_ident!("__{binding}_idx");
+                        let arms = dim.values.iter().enumerate().map(|(i, value)| {
+                            quote! { #i => #value, }
+                        });
+                        quote! {
+                            let #binding = match #idx {
+                                #(#arms)*
+                                _ => ::std::unreachable!(),
+                            };
+                        }
+                    })
+                    .collect::<Vec<_>>();
+                // args with names aren't needed for the label, so they are only evaluated if
+                // the benchmark is run
+                let deferred_bindings = dims
+                    .iter()
+                    .zip(&value_bindings)
+                    .filter(|(dim, _)| dim.names.is_some())
+                    .map(|(_, binding)| binding);
+                let mut body = quote! {
+                    let label = #label_root #(#label_parts)*;
+                    if ::haste::__private::should_run(&mut b, &label) {
+                        #force_runtime
+                        #(#deferred_bindings)*
+                        #bench_call
+                    }
+                };
+                // One nested loop per parameter to run the cartesian product of all args. The
+                // values are evaluated lazily, so only one value per parameter is alive at a time.
+                for (dim, value_binding) in dims.iter().zip(&value_bindings).rev() {
+                    let idx = format_ident!("__{}_idx", dim.binding);
+                    let count = dim.values.len();
+                    let value_binding = dim.names.is_none().then_some(value_binding);
+                    body = quote! {
+                        for #idx in 0..#count {
+                            #value_binding
+                            #body
+                        }
+                    };
+                }
+                quote! {
+                    #config_calls
+                    #runtime_tokens
                     #body
                 }
             }
-        }
-    });
+            (count, _) => {
+                return Error::new_spanned(
+                    func,
+                    format!("Unsupported number of parameters: {count}"),
+                )
+                .into_compile_error();
+            }
+        };
 
-    if let Some(bench_module) = bench_module {
-        let bench_module: ItemMod = syn::parse2(bench_module)
-            .expect("error in macro expansion. bench_module can't be parsed as ItemMod");
-        func.block
-            .stmts
-            .push(Stmt::Item(syn::Item::Mod(bench_module)));
-        func.to_token_stream()
-    } else {
-        quote! {
+        // `b` is only passed on if the function takes `Haste` and doesn't configure it
+        let b_mut = (func.sig.inputs.len() != 1
+            || dims.is_some()
+            || !config_calls.is_empty()
+            || throughput.is_some())
+        .then(|| quote! {mut});
+        // generic functions are registered once per instantiation
+        let bench_fn_ident = format_ident!("{ident}_{idx}");
+        let bench_static_ident = format_ident!("BENCH_{idx}");
+        let group = match &group {
+            Some(group) => quote! { ::std::option::Option::Some(#group) },
+            None => quote! { ::std::option::Option::None },
+        };
+        bench_fns.push(quote! {
             #[::haste::__private::distributed_slice(haste::__private::BENCHMARKS)]
             #[linkme(crate = haste::__private::linkme)]
-            #func
+            static #bench_static_ident: ::haste::__private::Benchmark =
+                ::haste::__private::Benchmark {
+                    name: #ident_str,
+                    module_path: ::std::module_path!(),
+                    group: #group,
+                    ignored: #ignore,
+                    instance: #idx,
+                    func: #bench_fn_ident,
+                };
+
+            fn #bench_fn_ident(#b_mut b: ::haste::Haste) {
+                #bench_body
+            }
+        });
+    }
+
+    let bench_module = quote! {
+        #[doc(hidden)]
+        mod __haste_bench {
+            use super::*;
+            use ::haste::__private::{DebugLabelPart as _, DisplayLabelPart as _};
+
+            #(#bench_fns)*
         }
+    };
+    let bench_module: ItemMod = syn::parse2(bench_module)
+        .expect("error in macro expansion. bench_module can't be parsed as ItemMod");
+    // insert at the start, as the last statement might be the tail expression of the function
+    func.block
+        .stmts
+        .insert(0, Stmt::Item(syn::Item::Mod(bench_module)));
+    func.to_token_stream()
+}
+
+/// One instantiation of the generic parameters of the benchmarked function.
+struct Instance {
+    /// The turbofish with the generic arguments, e.g. `::<16>`, or empty for non-generic
+    /// functions.
+    generic_args: TokenStream,
+    /// Label parts identifying the instantiation.
+    label_parts: Vec<TokenStream>,
+}
+
+/// Returns the instantiations of the generic parameters of the function with the provided
+/// `consts` and `types`. If the function has both a const and a type parameter, the cartesian
+/// product of both is returned.
+fn generic_instances(
+    func: &ItemFn,
+    consts: Option<Punctuated<Expr, Token![,]>>,
+    types: Option<Punctuated<Type, Token![,]>>,
+) -> syn::Result<Vec<Instance>> {
+    let mut consts = consts.map(|consts| {
+        consts
+            .into_iter()
+            .map(|value| (quote! { { #value } }, quote! { #value }))
+            .collect::<Vec<_>>()
+    });
+    let mut types = types.map(|types| {
+        types
+            .into_iter()
+            .map(|ty| {
+                let name = type_label(&ty);
+                (quote! { #ty }, quote! { #name })
+            })
+            .collect::<Vec<_>>()
+    });
+
+    // the (generic arg, label part) choices for each generic parameter in declaration order
+    let mut choices = Vec::new();
+    let (mut has_const_param, mut has_type_param) = (false, false);
+    for param in &func.sig.generics.params {
+        let (values, has_param, attr) = match param {
+            GenericParam::Const(_) => (consts.take(), &mut has_const_param, "consts"),
+            GenericParam::Type(_) => (types.take(), &mut has_type_param, "types"),
+            GenericParam::Lifetime(_) => continue,
+        };
+        let Some(values) = values else {
+            let msg = if *has_param {
+                format!("only one generic parameter with {attr} = [..] is supported")
+            } else {
+                format!("generic benchmarks require {attr} = [..]")
+            };
+            return Err(Error::new_spanned(param, msg));
+        };
+        *has_param = true;
+        choices.push(values);
     }
+    if consts.is_some() {
+        return Err(Error::new_spanned(
+            &func.sig,
+            "consts require a function with a const generic parameter",
+        ));
+    }
+    if types.is_some() {
+        return Err(Error::new_spanned(
+            &func.sig,
+            "types require a function with a type parameter",
+        ));
+    }
+
+    let mut instances = vec![(Vec::new(), Vec::new())];
+    for values in choices {
+        instances = instances
+            .into_iter()
+            .flat_map(|(args, parts)| {
+                values.iter().map(move |(arg, part)| {
+                    let mut args = args.clone();
+                    let mut parts = parts.clone();
+                    args.push(arg.clone());
+                    parts.push(part.clone());
+                    (args, parts)
+                })
+            })
+            .collect();
+    }
+
+    Ok(instances
+        .into_iter()
+        .map(|(args, label_parts)| Instance {
+            generic_args: if args.is_empty() {
+                TokenStream::new()
+            } else {
+                quote! { ::<#(#args),*> }
+            },
+            label_parts,
+        })
+        .collect())
+}
+
+/// Formats a type for a label, e.g. `Vec<u8>` instead of `Vec < u8 >`.
+fn type_label(ty: &Type) -> String {
+    let tokens = ty.to_token_stream().to_string();
+    let chars: Vec<char> = tokens.chars().collect();
+    let is_word = |c: Option<&char>| c.is_some_and(|c| c.is_alphanumeric() || *c == '_');
+    chars
+        .iter()
+        .enumerate()
+        .filter(|&(idx, &c)| {
+            // only keep spaces which separate two words, e.g. in `dyn Trait`
+            c != ' ' || (is_word(chars.get(idx.wrapping_sub(1))) && is_word(chars.get(idx + 1)))
+        })
+        .map(|(_, c)| c)
+        .collect()
+}
+
+/// Matches the named args to the parameters of the function. The returned dimensions are in the
+/// order of the parameters.
+fn named_arg_dims(
+    func: &ItemFn,
+    mut named: Vec<(Ident, Punctuated<Expr, Token![,]>)>,
+) -> syn::Result<Vec<ArgDim>> {
+    let mut dims = Vec::with_capacity(named.len());
+    for input in &func.sig.inputs {
+        let FnArg::Typed(pat_type) = input else {
+            return Err(Error::new_spanned(
+                input,
+                "self parameters are not supported",
+            ));
+        };
+        let Pat::Ident(pat_ident) = &*pat_type.pat else {
+            return Err(Error::new_spanned(
+                &pat_type.pat,
+                "parameters with named args must be identifiers",
+            ));
+        };
+        let param = &pat_ident.ident;
+        let Some(pos) = named.iter().position(|(name, _)| name == param) else {
+            return Err(Error::new_spanned(
+                param,
+                format!("missing args for parameter {param}"),
+            ));
+        };
+        let (binding, values) = named.remove(pos);
+        dims.push(ArgDim {
+            binding,
+            values,
+            names: None,
+            named: true,
+            by_ref: is_reference_param(input),
+        });
+    }
+    if let Some((name, _)) = named.first() {
+        return Err(Error::new_spanned(
+            name,
+            format!("no parameter named {name}"),
+        ));
+    }
+    Ok(dims)
+}
+
+fn is_reference_param(param: &FnArg) -> bool {
+    matches!(param, FnArg::Typed(pat_type) if matches!(*pat_type.ty, Type::Reference(_)))
 }
@@ -9,6 +9,47 @@ use crate::{
 mod args;
 mod func;
 
+/// Registers a function as a benchmark.
+///
+/// The benchmark is labeled with its module path and function name, e.g. `json::parse`. The
+/// crate name is stripped from the module path unless disabled with
+/// `Runner::strip_crate_prefix`.
+///
+/// To list and sort the benchmarks, the function is called to collect their labels before any
+/// benchmark is run, and then once more for each benchmark which is run. This evaluates the
+/// `args` which are needed for the labels each time, but the `setup` and `runtime` only once per
+/// run benchmark. A function taking `Haste` chooses its own labels, so all of its code before
+/// the `bench*` calls runs each time. Create expensive inputs in the setup of
+/// `Haste::bench_with_input` or `Haste::bench_batched` instead, which is only called if the
+/// benchmark is run.
+///
+/// Supported arguments:
+/// - `args = [..]`: run the benchmark once for each of the provided values. The values are
+///   evaluated lazily, one benchmark at a time. If the parameter is a reference, e.g. `&Vec<u8>`,
+///   the value is passed by reference and doesn't need to be `Copy`. The values are formatted
+///   with `Display` in the label, or `Debug` if they don't implement `Display`.
+/// - `arg_names = [..]`: one name per value of `args = [..]`, used in the label instead of the
+///   formatted value. The values are then only evaluated if the benchmark is run.
+/// - `setup = ..`: a function which is called with each arg to create the input of the
+///   benchmark outside of the measurement. The benchmarked function receives a reference to the
+///   input.
+/// - `args(a = [..], b = [..])`: values for each parameter of a function with multiple
+///   parameters. The benchmark is run for the cartesian product of all values.
+/// - `consts = [..]`: values for the const generic parameter of the function. A separate
+///   benchmark is registered for each value.
+/// - `types = [..]`: types for the type parameter of the function. A separate benchmark is
+///   registered for each type.
+/// - `group = ".."`: a group which replaces the module path in the label, e.g.
+///   `group = "parsing"` results in `parsing::parse`
+/// - `ignore`: only run the benchmark with `--ignored` or `--include-ignored`
+/// - `runtime = ..`: the runtime to use for `async` benchmarks
+/// - `throughput = ..`: a [`Throughput`](https://docs.rs/haste/latest/haste/throughput/enum.Throughput.html),
+///   which can refer to the current `arg`
+/// - `warmup = ..`, `sample_time = ..`: a `Duration`
+/// - `sample_count = ..`: the number of samples to collect
+/// - `sampling_mode = ..`: a `SamplingMode`
+/// - `drop_mode = ..`: a `DropMode`
+/// - `timer = ..`: a `Timer` which measures the benchmark, e.g. `haste::timer::ThreadCpuTime`
 #[proc_macro_attribute]
 pub fn bench(args: TokenStream, input: TokenStream) -> TokenStream {
     let args = parse_macro_input!(args as Args);
@@ -0,0 +1,281 @@
+//! Counting of heap allocations made by benchmarks.
+//!
+//! Register a [`CountingAllocator`] as the global allocator of your benchmark binary to report
+//! the allocations per iteration of each benchmark:
+//!
+//! ```no_run
+//! use std::alloc::System;
+//!
+//! use haste::alloc::CountingAllocator;
+//!
+//! #[global_allocator]
+//! static ALLOC: CountingAllocator = CountingAllocator::new(System);
+//!
+//! fn main() {
+//!     haste::main();
+//! }
+//! ```
+//!
+//! Allocations are only counted while a benchmarked function is measured, but on all threads.
+use std::{
+    alloc::{GlobalAlloc, Layout, System},
+    sync::atomic::{AtomicBool, AtomicI64, AtomicU64, Ordering::Relaxed},
+};
+
+static INSTALLED: AtomicBool = AtomicBool::new(false);
+static COUNTERS: Counters = Counters::new();
+
+/// The counters of allocations, which are only updated while recording.
+struct Counters {
+    recording: AtomicBool,
+    allocs: AtomicU64,
+    deallocs: AtomicU64,
+    bytes: AtomicU64,
+    /// Bytes allocated minus bytes deallocated while recording. Can be negative if memory
+    /// allocated before recording is deallocated.
+    live_bytes: AtomicI64,
+    peak_bytes: AtomicI64,
+}
+
+impl Counters {
+    const fn new() -> Self {
+        Self {
+            recording: AtomicBool::new(false),
+            allocs: AtomicU64::new(0),
+            deallocs: AtomicU64::new(0),
+            bytes: AtomicU64::new(0),
+            live_bytes: AtomicI64::new(0),
+            peak_bytes: AtomicI64::new(0),
+        }
+    }
+
+    fn record_alloc(&self, size: usize) {
+        if self.recording.load(Relaxed) {
+            self.allocs.fetch_add(1, Relaxed);
+            self.bytes.fetch_add(size as u64, Relaxed);
+            let live = self.live_bytes.fetch_add(size as i64, Relaxed) + size as i64;
+            self.peak_bytes.fetch_max(live, Relaxed);
+        }
+    }
+
+    fn record_dealloc(&self, size: usize) {
+        if self.recording.load(Relaxed) {
+            self.deallocs.fetch_add(1, Relaxed);
+            self.live_bytes.fetch_sub(size as i64, Relaxed);
+        }
+    }
+
+    /// Counted as a deallocation of the old and an allocation of the new memory.
+    fn record_realloc(&self, old_size: usize, new_size: usize) {
+        self.record_dealloc(old_size);
+        self.record_alloc(new_size);
+    }
+
+    fn take_counts(&self) -> AllocCounts {
+        let counts = AllocCounts {
+            allocs: self.allocs.swap(0, Relaxed),
+            deallocs: self.deallocs.swap(0, Relaxed),
+            bytes: self.bytes.swap(0, Relaxed),
+            peak_bytes: self.peak_bytes.swap(0, Relaxed).max(0) as u64,
+        };
+        self.live_bytes.store(0, Relaxed);
+        counts
+    }
+}
+
+/// A [`GlobalAlloc`] which counts the allocations of benchmarks and forwards them to the
+/// wrapped allocator, e.g. [`System`].
+#[derive(Debug, Default)]
+pub struct CountingAllocator<A = System> {
+    inner: A,
+}
+
+impl<A> CountingAllocator<A> {
+    pub const fn new(inner: A) -> Self {
+        Self { inner }
+    }
+}
+
+fn mark_installed() {
+    if !INSTALLED.load(Relaxed) {
+        INSTALLED.store(true, Relaxed);
+    }
+}
+
+// Safety: All calls are forwarded to the wrapped allocator, which upholds the contract.
+unsafe impl<A: GlobalAlloc> GlobalAlloc for CountingAllocator<A> {
+    unsafe fn alloc(&self, layout: Layout) -> *mut u8 {
+        mark_installed();
+        COUNTERS.record_alloc(layout.size());
+        // Safety: the caller upholds the contract of `GlobalAlloc::alloc`
+        unsafe { self.inner.alloc(layout) }
+    }
+
+    unsafe fn alloc_zeroed(&self, layout: Layout) -> *mut u8 {
+        mark_installed();
+        COUNTERS.record_alloc(layout.size());
+        // Safety: the caller upholds the contract of `GlobalAlloc::alloc_zeroed`
+        unsafe { self.inner.alloc_zeroed(layout) }
+    }
+
+    unsafe fn dealloc(&self, ptr: *mut u8, layout: Layout) {
+        COUNTERS.record_dealloc(layout.size());
+        // Safety: the caller upholds the contract of `GlobalAlloc::dealloc`
+        unsafe { self.inner.dealloc(ptr, layout) }
+    }
+
+    unsafe fn realloc(&self, ptr: *mut u8, layout: Layout, new_size: usize) -> *mut u8 {
+        COUNTERS.record_realloc(layout.size(), new_size);
+        // Safety: the caller upholds the contract of `GlobalAlloc::realloc`
+        unsafe { self.inner.realloc(ptr, layout, new_size) }
+    }
+}
+
+/// Whether a [`CountingAllocator`] is the global allocator.
+pub(crate) fn is_installed() -> bool {
+    INSTALLED.load(Relaxed)
+}
+
+/// Starts counting allocations. Called right before the measurement of a benchmark starts.
+#[inline(always)]
+pub(crate) fn start_recording() {
+    COUNTERS.recording.store(true, Relaxed);
+}
+
+/// Stops counting allocations. Called right after the measurement of a benchmark ends.
+#[inline(always)]
+pub(crate) fn stop_recording() {
+    COUNTERS.recording.store(false, Relaxed);
+}
+
+/// The allocations recorded since the last call to [`take_counts`].
+#[derive(Clone, Copy, Debug, Default)]
+pub(crate) struct AllocCounts {
+    pub(crate) allocs: u64,
+    pub(crate) deallocs: u64,
+    pub(crate) bytes: u64,
+    /// The maximum increase of allocated memory while recording.
+    pub(crate) peak_bytes: u64,
+}
+
+/// Returns the recorded allocations and resets the counters.
+pub(crate) fn take_counts() -> AllocCounts {
+    COUNTERS.take_counts()
+}
+
+/// The heap allocations per iteration of a benchmark.
+#[derive(Clone, Copy, Debug)]
+pub struct AllocStats {
+    pub(crate) allocs: f64,
+    pub(crate) deallocs: f64,
+    pub(crate) bytes: f64,
+    pub(crate) peak_bytes: u64,
+}
+
+impl AllocStats {
+    /// Computes the stats per iteration from the counts of all sampled `iters` and the counts of
+    /// a single iteration, which determines the peak memory usage.
+    pub(crate) fn new(counts: AllocCounts, iters: usize, single: AllocCounts) -> Self {
+        let iters = iters as f64;
+        Self {
+            allocs: counts.allocs as f64 / iters,
+            deallocs: counts.deallocs as f64 / iters,
+            bytes: counts.bytes as f64 / iters,
+            peak_bytes: single.peak_bytes,
+        }
+    }
+
+    /// The mean number of allocations per iteration. Reallocations count as an allocation and
+    /// a deallocation.
+    pub fn allocs(&self) -> f64 {
+        self.allocs
+    }
+
+    /// The mean number of deallocations per iteration.
+    ///
+    /// With [`DropMode::Outside`](crate::DropMode::Outside), the returned values are dropped
+    /// after the measurement, so their deallocations are not included.
+    pub fn deallocs(&self) -> f64 {
+        self.deallocs
+    }
+
+    /// The mean number of allocated bytes per iteration.
+    pub fn bytes(&self) -> f64 {
+        self.bytes
+    }
+
+    /// The maximum increase of allocated memory during a single iteration.
+    pub fn peak_bytes(&self) -> u64 {
+        self.peak_bytes
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::atomic::Ordering::Relaxed;
+
+    use super::{AllocCounts, AllocStats, Counters};
+
+    #[test]
+    fn test_record_only_while_recording() {
+        let counters = Counters::new();
+        counters.record_alloc(100);
+        counters.record_dealloc(100);
+        counters.record_realloc(100, 200);
+        let counts = counters.take_counts();
+        assert_eq!((counts.allocs, counts.deallocs, counts.bytes), (0, 0, 0));
+        assert_eq!(counts.peak_bytes, 0);
+    }
+
+    #[test]
+    fn test_record_counts() {
+        let counters = Counters::new();
+        counters.recording.store(true, Relaxed);
+        counters.record_alloc(100);
+        counters.record_alloc(50);
+        counters.record_dealloc(100);
+        counters.record_realloc(50, 200);
+        counters.record_dealloc(200);
+        let counts = counters.take_counts();
+        assert_eq!(counts.allocs, 3);
+        assert_eq!(counts.deallocs, 3);
+        assert_eq!(counts.bytes, 350);
+        // 150 live bytes after the first two allocations, 200 after the reallocation
+        assert_eq!(counts.peak_bytes, 200);
+
+        let counts = counters.take_counts();
+        assert_eq!((counts.allocs, counts.deallocs, counts.bytes), (0, 0, 0));
+        assert_eq!(counts.peak_bytes, 0);
+    }
+
+    #[test]
+    fn test_peak_of_memory_allocated_before_recording() {
+        let counters = Counters::new();
+        counters.recording.store(true, Relaxed);
+        counters.record_dealloc(1000);
+        counters.record_alloc(100);
+        assert_eq!(counters.take_counts().peak_bytes, 0);
+        // the live bytes are reset with the counts
+        counters.record_alloc(100);
+        assert_eq!(counters.take_counts().peak_bytes, 100);
+    }
+
+    #[test]
+    fn test_alloc_stats_per_iteration() {
+        let counts = AllocCounts {
+            allocs: 30,
+            deallocs: 20,
+            bytes: 1000,
+            peak_bytes: 500,
+        };
+        let single = AllocCounts {
+            peak_bytes: 64,
+            ..AllocCounts::default()
+        };
+        let stats = AllocStats::new(counts, 10, single);
+        assert_eq!(stats.allocs(), 3.0);
+        assert_eq!(stats.deallocs(), 2.0);
+        assert_eq!(stats.bytes(), 100.0);
+        assert_eq!(stats.peak_bytes(), 64);
+    }
+}
@@ -0,0 +1,331 @@
+use std::{
+    collections::BTreeMap,
+    env,
+    fmt::Display,
+    fs, io,
+    path::{Path, PathBuf},
+};
+
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    bench_result::{BenchResult, Results},
+    config::Config,
+    sample::Sample,
+    stats::{self, ConfidenceInterval, Outliers},
+    timer::Unit,
+};
+
+const BASELINE_FILE: &str = "baseline.json";
+
+/// Saved results of a previous run, keyed by the benchmark label.
+#[derive(Debug, Default, Serialize, Deserialize)]
+pub(crate) struct Baseline {
+    benchmarks: BTreeMap<String, SavedBench>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+struct SavedBench {
+    unit: Unit,
+    mean: f64,
+    median: f64,
+    samples: Vec<Sample>,
+}
+
+impl Baseline {
+    /// Loads the baseline with the provided name from `<target_dir>/haste/<name>/`.
+    pub(crate) fn load(target_dir: &Path, name: &str) -> io::Result<Self> {
+        let content = fs::read_to_string(baseline_dir(target_dir, name).join(BASELINE_FILE))?;
+        serde_json::from_str(&content).map_err(io::Error::other)
+    }
+
+    /// Saves the baseline to `<target_dir>/haste/<name>/`, creating the directory if needed.
+    pub(crate) fn save(&self, target_dir: &Path, name: &str) -> io::Result<()> {
+        let dir = baseline_dir(target_dir, name);
+        fs::create_dir_all(&dir)?;
+        let content = serde_json::to_string_pretty(self).map_err(io::Error::other)?;
+        fs::write(dir.join(BASELINE_FILE), content)
+    }
+
+    /// Adds all results to the baseline, replacing existing entries with the same label.
+    pub(crate) fn extend(&mut self, results: &Results) {
+        for res in results.iter() {
+            let saved = SavedBench {
+                unit: res.unit,
+                mean: res.mean,
+                median: res.median,
+                samples: res.samples.clone(),
+            };
+            self.benchmarks.insert(res.label.to_string(), saved);
+        }
+    }
+
+    /// Compares the result with the saved result for the same label.
+    ///
+    /// Severe outliers are left out of both sides if the config excludes them from the
+    /// statistics.
+    pub(crate) fn compare(
+        &self,
+        res: &BenchResult,
+        config: &Config,
+    ) -> Result<Comparison, NoComparison> {
+        let saved = self
+            .benchmarks
+            .get(&res.label.to_string())
+            .ok_or(NoComparison::Missing)?;
+        if saved.unit != res.unit {
+            return Err(NoComparison::UnitMismatch {
+                baseline: saved.unit,
+                current: res.unit,
+            });
+        }
+        let old = included_iter_times(&saved.samples, config.exclude_severe_outliers);
+        let new = included_iter_times(&res.samples, config.exclude_severe_outliers);
+        if old.is_empty() || new.is_empty() {
+            return Err(NoComparison::Missing);
+        }
+        let (change, ci) = stats::relative_mean_change(
+            &old,
+            &new,
+            config.bootstrap_resamples,
+            config.confidence_level,
+        );
+        let verdict = if ci.lower > config.noise_threshold {
+            Verdict::Regressed
+        } else if ci.upper < -config.noise_threshold {
+            Verdict::Improved
+        } else {
+            Verdict::NoChange
+        };
+        Ok(Comparison {
+            change,
+            ci,
+            verdict,
+        })
+    }
+}
+
+/// Returns the iteration times of the samples, without severe outliers if they are excluded,
+/// like [`BenchResult::from_samples`] does for the statistics.
+fn included_iter_times(samples: &[Sample], exclude_severe_outliers: bool) -> Vec<f64> {
+    let iter_times: Vec<f64> = samples.iter().map(|s| s.iter_time).collect();
+    if !exclude_severe_outliers || iter_times.is_empty() {
+        return iter_times;
+    }
+    let outliers = Outliers::classify(&iter_times);
+    iter_times
+        .into_iter()
+        .filter(|&time| !outliers.is_severe(time))
+        .collect()
+}
+
+/// Reason why a result couldn't be compared to the baseline.
+#[derive(Debug, PartialEq, Eq)]
+pub(crate) enum NoComparison {
+    /// The baseline has no samples for the label.
+    Missing,
+    /// The baseline was measured with a timer of another unit.
+    UnitMismatch { baseline: Unit, current: Unit },
+}
+
+impl Display for NoComparison {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            NoComparison::Missing => write!(f, "Change: no baseline data"),
+            NoComparison::UnitMismatch { baseline, current } => write!(
+                f,
+                "Change: baseline was measured in {baseline}, not {current}"
+            ),
+        }
+    }
+}
+
+/// Change of the mean iteration time compared to a baseline.
+pub(crate) struct Comparison {
+    pub(crate) change: f64,
+    pub(crate) ci: ConfidenceInterval,
+    pub(crate) verdict: Verdict,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub(crate) enum Verdict {
+    Improved,
+    Regressed,
+    NoChange,
+}
+
+impl Display for Comparison {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let verdict = match self.verdict {
+            Verdict::Improved => "Improved",
+            Verdict::Regressed => "Regressed",
+            Verdict::NoChange => "No change",
+        };
+        write!(
+            f,
+            "Change: {:+.2}% [{:+.2}% {:+.2}%] | {verdict}",
+            self.change * 100.0,
+            self.ci.lower * 100.0,
+            self.ci.upper * 100.0,
+        )
+    }
+}
+
+fn baseline_dir(target_dir: &Path, name: &str) -> PathBuf {
+    target_dir.join("haste").join(name)
+}
+
+/// Returns the cargo target directory.
+///
+/// Uses `CARGO_TARGET_DIR` if set. Otherwise, the target directory is derived from the path of
+/// the bench executable, which cargo places at `<target>/<profile>/deps/`.
+pub(crate) fn target_dir() -> PathBuf {
+    if let Some(dir) = env::var_os("CARGO_TARGET_DIR") {
+        return dir.into();
+    }
+    env::current_exe()
+        .ok()
+        .as_deref()
+        .and_then(Path::parent)
+        .filter(|deps| deps.ends_with("deps"))
+        .and_then(Path::parent)
+        .and_then(Path::parent)
+        .map(Path::to_path_buf)
+        .unwrap_or_else(|| PathBuf::from("target"))
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{env, fs, path::PathBuf, process};
+
+    use super::{Baseline, NoComparison, Verdict};
+    use crate::{
+        bench_result::{BenchResult, Results},
+        config::Config,
+        sample::Sample,
+        sampling_mode::ActualSamplingMode,
+        timer::Unit,
+    };
+
+    fn config() -> Config {
+        Config {
+            bootstrap_resamples: 200,
+            ..Config::default()
+        }
+    }
+
+    fn result(label: &str, mean: f64, config: &Config) -> BenchResult {
+        let samples: Vec<Sample> = (0..20)
+            .map(|i| Sample {
+                iter_time: mean + (i % 5) as f64 * mean * 0.001,
+                sample_size: 10,
+            })
+            .collect();
+        let sampling_mode = ActualSamplingMode::Flat { sample_size: 10 };
+        BenchResult::from_samples(label.into(), &samples, sampling_mode, config)
+    }
+
+    fn baseline(res: BenchResult) -> Baseline {
+        let mut results = Results::default();
+        results.push(res);
+        let mut baseline = Baseline::default();
+        baseline.extend(&results);
+        baseline
+    }
+
+    #[test]
+    fn test_verdict() {
+        let config = config();
+        let baseline = baseline(result("fib", 100.0, &config));
+        let verdict = |mean| {
+            baseline
+                .compare(&result("fib", mean, &config), &config)
+                .unwrap()
+                .verdict
+        };
+        assert_eq!(verdict(150.0), Verdict::Regressed);
+        assert_eq!(verdict(50.0), Verdict::Improved);
+        assert_eq!(verdict(100.0), Verdict::NoChange);
+        // Significant, but below the noise threshold of 1%.
+        assert_eq!(verdict(100.5), Verdict::NoChange);
+        assert_eq!(verdict(99.5), Verdict::NoChange);
+    }
+
+    #[test]
+    fn test_compare_without_severe_outliers() {
+        let mut config = config();
+        let baseline = baseline(result("fib", 100.0, &config));
+        let mut res = result("fib", 100.0, &config);
+        res.samples[0].iter_time = 10_000.0;
+        let change = |config: &Config| baseline.compare(&res, config).unwrap().change;
+        assert!(change(&config) > 1.0);
+        config.exclude_severe_outliers = true;
+        assert!(change(&config).abs() < 0.01);
+    }
+
+    #[test]
+    fn test_no_comparison() {
+        let config = config();
+        let baseline = baseline(result("fib", 100.0, &config));
+        let missing = baseline.compare(&result("sort", 100.0, &config), &config);
+        assert_eq!(missing.err(), Some(NoComparison::Missing));
+
+        let mut res = result("fib", 100.0, &config);
+        res.unit = Unit::Cycles;
+        let mismatch = baseline.compare(&res, &config).err().unwrap();
+        assert_eq!(
+            mismatch,
+            NoComparison::UnitMismatch {
+                baseline: Unit::Nanoseconds,
+                current: Unit::Cycles,
+            }
+        );
+        assert_eq!(
+            mismatch.to_string(),
+            "Change: baseline was measured in ns, not cycles"
+        );
+    }
+
+    /// A temporary directory which is removed when dropped, even if the test fails.
+    struct TempDir(PathBuf);
+
+    impl TempDir {
+        fn new(name: &str) -> Self {
+            let dir = env::temp_dir().join(format!("haste-{name}-{}", process::id()));
+            fs::create_dir_all(&dir).unwrap();
+            Self(dir)
+        }
+    }
+
+    impl Drop for TempDir {
+        fn drop(&mut self) {
+            let _ = fs::remove_dir_all(&self.0);
+        }
+    }
+
+    #[test]
+    #[cfg_attr(miri, ignore)]
+    fn test_save_load() {
+        let target_dir = TempDir::new("save-load");
+        let name = "main";
+        let config = config();
+        let mut baseline = baseline(result("fib", 100.0, &config));
+        let mut results = Results::default();
+        results.push(result("fib", 200.0, &config));
+        results.push(result("sort", 300.0, &config));
+        baseline.extend(&results);
+        baseline.save(&target_dir.0, name).unwrap();
+        assert!(target_dir.0.join("haste/main/baseline.json").is_file());
+        let loaded = Baseline::load(&target_dir.0, name).unwrap();
+
+        assert_eq!(loaded.benchmarks.len(), 2);
+        let fib = &loaded.benchmarks["fib"];
+        assert_eq!(fib.unit, Unit::Nanoseconds);
+        assert_eq!(fib.mean, baseline.benchmarks["fib"].mean);
+        assert_eq!(fib.samples.len(), 20);
+        let comparison = loaded
+            .compare(&result("sort", 300.0, &config), &config)
+            .unwrap();
+        assert_eq!(comparison.verdict, Verdict::NoChange);
+    }
+}
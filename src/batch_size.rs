@@ -0,0 +1,48 @@
+/// Determines how many inputs are created at once by [`Haste::bench_batched`](crate::Haste::bench_batched).
+///
+/// Inputs are created in batches outside of the measurement and then consumed by the benchmarked
+/// routine. Larger batches reduce the timing overhead per iteration, but need more memory.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum BatchSize {
+    /// The inputs are small, e.g. a few bytes. Each sample is split into 10 batches.
+    SmallInput,
+    /// The inputs are large, e.g. a large `Vec`. Each sample is split into 1000 batches.
+    LargeInput,
+    /// Each iteration is its own batch and measured individually. This has the highest
+    /// measurement overhead and should only be used if a single input is very large.
+    PerIteration,
+}
+
+impl BatchSize {
+    /// Returns the number of iterations per batch for a sample of `sample_size` iterations.
+    pub(crate) fn iters_per_batch(self, sample_size: usize) -> usize {
+        match self {
+            BatchSize::SmallInput => sample_size.div_ceil(10),
+            BatchSize::LargeInput => sample_size.div_ceil(1000),
+            BatchSize::PerIteration => 1,
+        }
+        .max(1)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::BatchSize;
+
+    #[test]
+    fn test_iters_per_batch() {
+        assert_eq!(BatchSize::SmallInput.iters_per_batch(1000), 100);
+        assert_eq!(BatchSize::LargeInput.iters_per_batch(1000), 1);
+        assert_eq!(BatchSize::PerIteration.iters_per_batch(1000), 1);
+        assert_eq!(BatchSize::SmallInput.iters_per_batch(25), 3);
+        assert_eq!(BatchSize::LargeInput.iters_per_batch(5000), 5);
+        for batch_size in [
+            BatchSize::SmallInput,
+            BatchSize::LargeInput,
+            BatchSize::PerIteration,
+        ] {
+            assert_eq!(batch_size.iters_per_batch(0), 1, "{batch_size:?}");
+            assert_eq!(batch_size.iters_per_batch(1), 1, "{batch_size:?}");
+        }
+    }
+}
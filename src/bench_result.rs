@@ -1,88 +1,396 @@
 use std::{
+    any::TypeId,
     fmt::{Display, Write},
     time::Duration,
 };
 
-use crate::{Label, sample::Sample, throughput::Throughput};
+#[cfg(feature = "perf")]
+use crate::perf::PerfStats;
+use crate::{
+    Label,
+    alloc::AllocStats,
+    config::Config,
+    drop_mode::DropMode,
+    sample::Sample,
+    sampling_mode::ActualSamplingMode,
+    stats::{self, Bootstrap, ConfidenceInterval, Outliers, Regression},
+    throughput::Throughput,
+    timer::{Calibration, Unit},
+};
 
-#[derive(Default)]
+/// The results of all benchmarks run with a [`Haste`](crate::Haste) instance.
+///
+/// ```no_run
+/// use haste::{Haste, Results};
+///
+/// let mut results = Results::default();
+/// Haste::new(&mut results).bench("add", || 1 + 1);
+/// for res in &results {
+///     println!("{}: {:.2} {}", res.label(), res.mean(), res.unit());
+/// }
+/// ```
+#[derive(Debug, Default)]
 pub struct Results {
     results: Vec<BenchResult>,
+    /// The labels of the selected benchmarks when only listing them.
+    pub(crate) listed: Vec<Label>,
+    /// The calibration of each type of timer used so far.
+    pub(crate) calibrations: Vec<(TypeId, Calibration)>,
 }
 
 impl Results {
     pub(crate) fn push(&mut self, value: BenchResult) {
         self.results.push(value);
     }
+
+    /// Iterates over the results in the order the benchmarks were run.
+    pub fn iter(&self) -> impl Iterator<Item = &BenchResult> {
+        self.results.iter()
+    }
+
+    /// Returns the result of the benchmark with the provided label, e.g. `"bench_fib/2"`.
+    pub fn get(&self, label: &str) -> Option<&BenchResult> {
+        self.results.iter().find(|res| res.label.displays_as(label))
+    }
+
+    pub fn len(&self) -> usize {
+        self.results.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.results.is_empty()
+    }
+}
+
+impl<'a> IntoIterator for &'a Results {
+    type Item = &'a BenchResult;
+    type IntoIter = std::slice::Iter<'a, BenchResult>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.results.iter()
+    }
 }
 
+/// The result of a single benchmark.
+///
+/// All times are per iteration of the benchmarked function and given in the [`Unit`] of the
+/// timer, which is nanoseconds by default.
 #[derive(Debug)]
-pub(crate) struct BenchResult {
+pub struct BenchResult {
     pub(crate) label: Label,
-    pub(crate) min_ns: f64,
-    pub(crate) max_ns: f64,
-    pub(crate) mean_ns: f64,
+    pub(crate) unit: Unit,
+    pub(crate) min: f64,
+    pub(crate) max: f64,
+    pub(crate) mean: f64,
+    pub(crate) median: f64,
+    pub(crate) mean_ci: ConfidenceInterval,
+    pub(crate) median_ci: ConfidenceInterval,
+    /// Least-squares fit of total sample time against sample size. Only present for
+    /// [`ActualSamplingMode::Linear`]. The slope of the fit is an estimate of the per-iteration
+    /// time and the intercept of the fixed per-sample overhead.
+    pub(crate) regression: Option<Regression>,
+    pub(crate) sample_count: usize,
+    pub(crate) outliers: Outliers,
+    /// Whether severe outliers were excluded from the summary statistics.
+    pub(crate) severe_outliers_excluded: bool,
     pub(crate) throughput: Option<Throughput>,
+    /// Only present if a [`CountingAllocator`](crate::alloc::CountingAllocator) is the global
+    /// allocator.
+    pub(crate) alloc_stats: Option<AllocStats>,
+    /// Only present if hardware counters are available.
+    #[cfg(feature = "perf")]
+    pub(crate) perf_stats: Option<PerfStats>,
+    /// The overhead of the benchmark loop per iteration. Only present if it was subtracted.
+    pub(crate) loop_overhead: Option<f64>,
+    pub(crate) samples: Vec<Sample>,
+    pub(crate) sampling_mode: ActualSamplingMode,
+    /// The config the benchmark was run with.
+    pub(crate) config: Config,
 }
 
 impl BenchResult {
-    pub(crate) fn from_samples(label: Label, samples: &[Sample]) -> BenchResult {
+    pub(crate) fn from_samples(
+        label: Label,
+        samples: &[Sample],
+        sampling_mode: ActualSamplingMode,
+        config: &Config,
+    ) -> BenchResult {
+        let all_iter_times: Vec<f64> = samples.iter().map(|s| s.iter_time).collect();
+        let outliers = Outliers::classify(&all_iter_times);
+        let severe_outliers_excluded =
+            config.exclude_severe_outliers && outliers.low_severe + outliers.high_severe > 0;
+        let mut iter_times: Vec<f64> = all_iter_times
+            .into_iter()
+            .filter(|&time| !(severe_outliers_excluded && outliers.is_severe(time)))
+            .collect();
+
         let [min, max, sum] =
-            samples
+            iter_times
                 .iter()
-                .fold([f64::MAX, 0.0, 0.0], |[min, max, mut sum], sample| {
-                    let min = min.min(sample.iter_time_ns);
-                    let max = max.max(sample.iter_time_ns);
-                    sum += sample.iter_time_ns;
+                .fold([f64::MAX, 0.0, 0.0], |[min, max, mut sum], &time| {
+                    let min = min.min(time);
+                    let max = max.max(time);
+                    sum += time;
                     [min, max, sum]
                 });
 
-        let mean = sum / samples.len() as f64;
+        let mean = sum / iter_times.len() as f64;
+
+        let bootstrap = Bootstrap::new(
+            &iter_times,
+            config.bootstrap_resamples,
+            config.confidence_level,
+        );
+        stats::sort(&mut iter_times);
+        let median = stats::percentile_of_sorted(&iter_times, 50.0);
+
+        let regression = match sampling_mode {
+            ActualSamplingMode::Linear { .. } => {
+                let (sizes, totals): (Vec<f64>, Vec<f64>) = samples
+                    .iter()
+                    .filter(|s| !(severe_outliers_excluded && outliers.is_severe(s.iter_time)))
+                    .map(|s| (s.sample_size as f64, s.iter_time * s.sample_size as f64))
+                    .unzip();
+                Regression::fit(&sizes, &totals)
+            }
+            ActualSamplingMode::Flat { .. } => None,
+        };
 
         BenchResult {
             label,
-            min_ns: min,
-            max_ns: max,
-            mean_ns: mean,
+            unit: config.timer.unit(),
+            min,
+            max,
+            mean,
+            median,
+            mean_ci: bootstrap.mean,
+            median_ci: bootstrap.median,
+            regression,
+            sample_count: samples.len(),
+            outliers,
+            severe_outliers_excluded,
             throughput: None,
+            alloc_stats: None,
+            #[cfg(feature = "perf")]
+            perf_stats: None,
+            loop_overhead: None,
+            samples: samples.to_vec(),
+            sampling_mode,
+            config: config.clone(),
         }
     }
 
+    pub fn label(&self) -> &Label {
+        &self.label
+    }
+
+    /// The unit of all times of this result.
+    pub fn unit(&self) -> Unit {
+        self.unit
+    }
+
+    pub fn min(&self) -> f64 {
+        self.min
+    }
+
+    pub fn max(&self) -> f64 {
+        self.max
+    }
+
+    pub fn mean(&self) -> f64 {
+        self.mean
+    }
+
+    pub fn median(&self) -> f64 {
+        self.median
+    }
+
+    /// Bootstrap confidence interval of the mean.
+    pub fn mean_ci(&self) -> ConfidenceInterval {
+        self.mean_ci
+    }
+
+    /// Bootstrap confidence interval of the median.
+    pub fn median_ci(&self) -> ConfidenceInterval {
+        self.median_ci
+    }
+
+    /// Least-squares fit of total sample time against sample size. Only present if the
+    /// benchmark used linear sampling.
+    pub fn regression(&self) -> Option<Regression> {
+        self.regression
+    }
+
+    pub fn outliers(&self) -> Outliers {
+        self.outliers
+    }
+
+    /// Whether severe outliers were excluded from the summary statistics.
+    pub fn severe_outliers_excluded(&self) -> bool {
+        self.severe_outliers_excluded
+    }
+
+    pub fn throughput(&self) -> Option<Throughput> {
+        self.throughput
+    }
+
+    /// The heap allocations per iteration. Only present if a
+    /// [`CountingAllocator`](crate::alloc::CountingAllocator) is the global allocator.
+    pub fn alloc_stats(&self) -> Option<AllocStats> {
+        self.alloc_stats
+    }
+
+    /// The hardware counters per iteration. Only present if counters are available.
+    #[cfg(feature = "perf")]
+    pub fn perf_stats(&self) -> Option<&PerfStats> {
+        self.perf_stats.as_ref()
+    }
+
+    /// The overhead of the benchmark loop per iteration, which was subtracted from all times.
+    /// Only present if enabled with [`Haste::with_loop_overhead_subtracted`](crate::Haste::with_loop_overhead_subtracted).
+    pub fn loop_overhead(&self) -> Option<f64> {
+        self.loop_overhead
+    }
+
+    /// The samples, including outliers. Their iteration times are net of the loop overhead if
+    /// it was subtracted.
+    pub fn samples(&self) -> &[Sample] {
+        &self.samples
+    }
+
     pub(crate) fn with_throughput(self, throughput: Throughput) -> BenchResult {
         Self {
             throughput: Some(throughput),
             ..self
         }
     }
+
+    #[cfg(feature = "perf")]
+    pub(crate) fn with_perf_stats(self, perf_stats: PerfStats) -> BenchResult {
+        Self {
+            perf_stats: Some(perf_stats),
+            ..self
+        }
+    }
+
+    pub(crate) fn with_loop_overhead(self, loop_overhead: f64) -> BenchResult {
+        Self {
+            loop_overhead: Some(loop_overhead),
+            ..self
+        }
+    }
+
+    pub(crate) fn with_alloc_stats(self, alloc_stats: AllocStats) -> BenchResult {
+        Self {
+            alloc_stats: Some(alloc_stats),
+            ..self
+        }
+    }
 }
 
 impl Display for BenchResult {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
-        let min = scale_nanos(self.min_ns);
-        let max = scale_nanos(self.max_ns);
-        let mean = scale_nanos(self.mean_ns);
+        let unit = self.unit;
+        let min = unit.scale(self.min);
+        let max = unit.scale(self.max);
+        let mean = unit.scale(self.mean);
         write!(
             f,
             "{}: Min: {:.2} | Mean: {:.2} | Max: {:.2}",
             self.label, min, mean, max
         )?;
-        if let Some(throughput) = self.throughput {
-            let min = scale_throughput(self.min_ns, throughput);
-            let max = scale_throughput(self.max_ns, throughput);
-            let mean = scale_throughput(self.mean_ns, throughput);
+        f.write_char('\n')?;
+        write!(
+            f,
+            "{:.0}% CI: Mean: [{:.2} {:.2}] | Median: {:.2} [{:.2} {:.2}]",
+            self.mean_ci.confidence_level * 100.0,
+            unit.scale(self.mean_ci.lower),
+            unit.scale(self.mean_ci.upper),
+            unit.scale(self.median),
+            unit.scale(self.median_ci.lower),
+            unit.scale(self.median_ci.upper),
+        )?;
+        if self.outliers.total() > 0 {
+            let o = &self.outliers;
+            f.write_char('\n')?;
+            write!(
+                f,
+                "Outliers: {} / {} | Low severe: {} | Low mild: {} | High mild: {} | High severe: {}",
+                o.total(),
+                self.sample_count,
+                o.low_severe,
+                o.low_mild,
+                o.high_mild,
+                o.high_severe
+            )?;
+            if self.severe_outliers_excluded {
+                f.write_str(" (severe excluded)")?;
+            }
+        }
+        if let Some(reg) = self.regression {
+            f.write_char('\n')?;
+            write!(
+                f,
+                "Slope: {:.2} | Intercept: {:.2} | R²: {:.4}",
+                unit.scale(reg.slope),
+                unit.scale(reg.intercept),
+                reg.r_squared
+            )?;
+        }
+        if let Some(loop_overhead) = self.loop_overhead {
+            f.write_char('\n')?;
+            write!(
+                f,
+                "Loop overhead: {:.2} (subtracted)",
+                unit.scale(loop_overhead)
+            )?;
+        }
+        // throughput is per second, so it can only be computed from times
+        if let Some(throughput) = self.throughput
+            && unit == Unit::Nanoseconds
+        {
+            let min = scale_throughput(self.min, throughput);
+            let max = scale_throughput(self.max, throughput);
+            let mean = scale_throughput(self.mean, throughput);
             f.write_char('\n')?;
             write!(
                 f,
                 "Throughput: Min: {min:.2} | Mean: {mean:.2}| Max: {max:.2}",
             )?;
         }
+        if let Some(alloc) = self.alloc_stats {
+            // return values dropped after the measurement are not counted
+            let deallocs_note = match self.config.drop_mode {
+                DropMode::Outside => " (excl. returned values)",
+                DropMode::Inside | DropMode::Leak => "",
+            };
+            f.write_char('\n')?;
+            write!(
+                f,
+                "Allocs: {:.2} | Deallocs: {:.2}{deallocs_note} | Bytes: {:.2} | Peak: {:.2}",
+                alloc.allocs,
+                alloc.deallocs,
+                scale_bytes(alloc.bytes),
+                scale_bytes(alloc.peak_bytes as f64)
+            )?;
+        }
+        #[cfg(feature = "perf")]
+        if let Some(perf) = &self.perf_stats {
+            f.write_char('\n')?;
+            for (idx, (counter, count)) in perf.iter().enumerate() {
+                if idx > 0 {
+                    f.write_str(" | ")?;
+                }
+                write!(f, "{counter}: {count:.2}")?;
+            }
+        }
         Ok(())
     }
 }
 
 pub(crate) struct Scaled {
-    val: f64,
-    unit: &'static str,
+    pub(crate) val: f64,
+    pub(crate) unit: &'static str,
 }
 
 impl Display for Scaled {
@@ -95,13 +403,15 @@ impl Display for Scaled {
 }
 
 pub(crate) fn scale_nanos(ns: f64) -> Scaled {
-    let (factor, unit) = if ns < 10_f64.powi(0) {
+    // use the magnitude so that negative values, e.g. a regression intercept, are scaled sensibly
+    let magnitude = ns.abs();
+    let (factor, unit) = if magnitude < 10_f64.powi(0) {
         (10_f64.powi(3), "ps")
-    } else if ns < 10_f64.powi(3) {
+    } else if magnitude < 10_f64.powi(3) {
         (10_f64.powi(0), "ns")
-    } else if ns < 10_f64.powi(6) {
+    } else if magnitude < 10_f64.powi(6) {
         (10_f64.powi(-3), "µs")
-    } else if ns < 10_f64.powi(9) {
+    } else if magnitude < 10_f64.powi(9) {
         (10_f64.powi(-6), "ms")
     } else {
         (10_f64.powi(-9), "s")
@@ -113,6 +423,22 @@ pub(crate) fn scale_nanos(ns: f64) -> Scaled {
     }
 }
 
+fn scale_bytes(bytes: f64) -> Scaled {
+    let (denom, unit) = if bytes < 1024.0_f64.powi(1) {
+        (1024.0_f64.powi(0), "B")
+    } else if bytes < 1024.0_f64.powi(2) {
+        (1024.0_f64.powi(1), "KiB")
+    } else if bytes < 1024.0_f64.powi(3) {
+        (1024.0_f64.powi(2), "MiB")
+    } else {
+        (1024.0_f64.powi(3), "GiB")
+    };
+    Scaled {
+        val: bytes / denom,
+        unit,
+    }
+}
+
 fn scale_throughput(ns: f64, throughput: Throughput) -> Scaled {
     let secs = Duration::from_nanos(ns as u64).as_secs_f64();
 
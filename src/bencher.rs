@@ -1,24 +1,58 @@
 use std::{
+    cell::Cell,
+    cmp,
     hint::black_box,
-    mem,
+    mem::{self, MaybeUninit},
+    sync::Arc,
     time::{Duration, Instant},
 };
 
 #[cfg(feature = "tokio")]
 use tokio::runtime::Runtime;
 
+#[cfg(feature = "perf")]
+use crate::perf::{self, PerfCounter, PerfStats};
+
 use crate::{
+    alloc::{self, AllocStats},
+    batch_size::BatchSize,
     bench_result::{BenchResult, Results, scale_nanos},
     config::Config,
+    drop_mode::DropMode,
     label::Label,
     sample::Sample,
-    sampling_mode::SamplingMode,
+    sampling_mode::{ActualSamplingMode, SamplingMode},
+    stats::{self, Regression},
     throughput::Throughput,
+    timer::{Calibration, Timer},
 };
 
+/// Configures and runs the benchmarks of a function registered with `#[haste::bench]`.
+///
+/// The function is called once to collect the labels of its benchmarks and once more for each
+/// benchmark which is run, where the other `bench*` calls return without running. Code before
+/// the `bench*` calls therefore runs multiple times, even with `--list`. Expensive inputs should
+/// be created in the `setup` of [`Haste::bench_with_input`] or [`Haste::bench_batched`], which
+/// is only called if the benchmark is run.
 pub struct Haste<'a> {
     pub(crate) config: Config,
     pub(crate) results: &'a mut Results,
+    selection: Selection,
+    /// The number of benchmarks which were selected by the filter so far.
+    selected: usize,
+    /// A label which was already selected before the benchmark is run, e.g. by
+    /// [`Haste::bench_with_input`] before it creates the input.
+    preselected: Option<Label>,
+}
+
+/// Which of the benchmarks selected by the filter are run.
+#[derive(Clone, Copy, Debug)]
+pub(crate) enum Selection {
+    All,
+    /// Only record the labels of the selected benchmarks in [`Results`].
+    Collect,
+    /// Only run the selected benchmark with this index.
+    Only(usize),
 }
 
 impl<'a> Haste<'a> {
@@ -26,6 +60,9 @@ impl<'a> Haste<'a> {
         Self {
             config: Config::default(),
             results,
+            selection: Selection::All,
+            selected: 0,
+            preselected: None,
         }
     }
     pub fn with_warmup(&mut self, duration: Duration) -> &mut Self {
@@ -38,53 +75,285 @@ impl<'a> Haste<'a> {
         self
     }
 
+    /// Sets the number of samples to collect. Must be positive.
     pub fn with_sample_count(&mut self, sample_count: usize) -> &mut Self {
+        assert!(sample_count > 0, "sample count must be positive");
         self.config.sample_count = sample_count;
         self
     }
 
+    pub fn with_sampling_mode(&mut self, sampling_mode: SamplingMode) -> &mut Self {
+        self.config.sampling_mode = sampling_mode;
+        self
+    }
+
+    /// Sets how the return values of the benchmarked function are dropped. See [`DropMode`].
+    pub fn with_drop_mode(&mut self, drop_mode: DropMode) -> &mut Self {
+        self.config.drop_mode = drop_mode;
+        self
+    }
+
+    /// Sets the hardware counters which are read for this benchmark. An empty slice disables
+    /// them. See [`perf`](crate::perf).
+    #[cfg(feature = "perf")]
+    pub fn with_perf_counters(&mut self, counters: &[PerfCounter]) -> &mut Self {
+        self.config.perf_counters = counters.to_vec();
+        self
+    }
+
+    /// Sets the timer which measures the benchmarked function. See [`timer`](crate::timer).
+    pub fn with_timer(&mut self, timer: impl Timer + 'static) -> &mut Self {
+        self.config.timer = Arc::new(timer);
+        self
+    }
+
+    /// Subtracts the calibrated overhead of reading the timer from each measurement.
+    pub fn with_timer_overhead_subtracted(&mut self, subtract: bool) -> &mut Self {
+        self.config.subtract_timer_overhead = subtract;
+        self
+    }
+
+    /// Subtracts the overhead of the benchmark loop from the iteration times. The overhead is
+    /// measured by benchmarking an empty function with the same return type and sample sizes.
+    pub fn with_loop_overhead_subtracted(&mut self, subtract: bool) -> &mut Self {
+        self.config.subtract_loop_overhead = subtract;
+        self
+    }
+
     pub fn with_throughput(&mut self, throughput: Throughput) -> &mut Self {
         self.config.throughput = Some(throughput);
         self
     }
 
+    /// Sets the confidence level of the reported confidence intervals. Must be in `(0, 1)`.
+    pub fn with_confidence_level(&mut self, confidence_level: f64) -> &mut Self {
+        assert!(
+            0.0 < confidence_level && confidence_level < 1.0,
+            "confidence level must be in (0, 1)"
+        );
+        self.config.confidence_level = confidence_level;
+        self
+    }
+
+    /// Sets the number of bootstrap resamples used to compute confidence intervals.
+    pub fn with_bootstrap_resamples(&mut self, resamples: usize) -> &mut Self {
+        assert!(
+            resamples > 0,
+            "number of bootstrap resamples must be positive"
+        );
+        self.config.bootstrap_resamples = resamples;
+        self
+    }
+
+    /// Excludes samples which are severe outliers (see Tukey's fences) from the reported
+    /// statistics. Outliers are counted and reported either way.
+    pub fn with_severe_outliers_excluded(&mut self, exclude: bool) -> &mut Self {
+        self.config.exclude_severe_outliers = exclude;
+        self
+    }
+
+    /// Sets the relative change of the mean compared to a baseline below which the change is
+    /// considered noise, e.g. `0.01` for 1%.
+    pub fn with_noise_threshold(&mut self, noise_threshold: f64) -> &mut Self {
+        assert!(
+            noise_threshold >= 0.0,
+            "noise threshold must be non-negative"
+        );
+        self.config.noise_threshold = noise_threshold;
+        self
+    }
+
     pub fn bench<R, F>(&mut self, label: impl Into<Label>, mut func: F)
     where
         F: FnMut() -> R,
     {
-        let label = label.into();
-        let c = &self.config;
+        let drop_mode = self.config.drop_mode;
+        self.bench_routine(
+            label.into(),
+            |sample_size, stopwatch| run_iters(&mut func, sample_size, drop_mode, stopwatch),
+            |sample_size, stopwatch| {
+                run_iters(
+                    &mut MaybeUninit::<R>::uninit,
+                    sample_size,
+                    drop_mode,
+                    stopwatch,
+                )
+            },
+        );
+    }
 
-        if let Some(regex) = &c.filter
-            && !regex.is_match(&label.to_string())
-        {
+    /// Benchmarks `routine` with inputs created by `setup`. Only the execution of `routine` is
+    /// measured.
+    ///
+    /// The inputs for a sample are created in batches whose size is determined by `batch_size`.
+    /// All inputs of a batch are kept in memory at the same time, so use
+    /// [`BatchSize::LargeInput`] or [`BatchSize::PerIteration`] for large inputs.
+    pub fn bench_batched<I, R, S, F>(
+        &mut self,
+        label: impl Into<Label>,
+        mut setup: S,
+        mut routine: F,
+        batch_size: BatchSize,
+    ) where
+        S: FnMut() -> I,
+        F: FnMut(I) -> R,
+    {
+        let drop_mode = self.config.drop_mode;
+        let routine = |sample_size, stopwatch: &Stopwatch| {
+            run_batches(
+                &mut setup,
+                &mut routine,
+                sample_size,
+                batch_size,
+                drop_mode,
+                stopwatch,
+            )
+        };
+        // the empty function consumes zero-sized inputs in the same batches
+        self.bench_routine(label.into(), routine, |sample_size, stopwatch| {
+            run_batches(
+                &mut || (),
+                &mut |()| MaybeUninit::<R>::uninit(),
+                sample_size,
+                batch_size,
+                drop_mode,
+                stopwatch,
+            )
+        });
+    }
+
+    /// Benchmarks `routine` with an input created by `setup` for each iteration. Only the
+    /// execution of `routine` is measured.
+    ///
+    /// This is equivalent to [`Haste::bench_batched`] with [`BatchSize::PerIteration`].
+    pub fn bench_with_setup<I, R, S, F>(&mut self, label: impl Into<Label>, setup: S, routine: F)
+    where
+        S: FnMut() -> I,
+        F: FnMut(I) -> R,
+    {
+        self.bench_batched(label, setup, routine, BatchSize::PerIteration);
+    }
+
+    /// Benchmarks `func` with a reference to an input created once by `setup`.
+    ///
+    /// `setup` is only called if the benchmark is not filtered out, so it can be used for
+    /// expensive inputs like large vectors or parsed files.
+    pub fn bench_with_input<I, R, S, F>(&mut self, label: impl Into<Label>, setup: S, mut func: F)
+    where
+        S: FnOnce() -> I,
+        F: FnMut(&I) -> R,
+    {
+        let label = self.prepare_label(label.into());
+        if !self.preselect(&label) {
             return;
         }
-        let (warmup_time, warmup_iters) = self.warmup(&mut func);
-        let sampling_mode = SamplingMode::decide_mode(c, warmup_time, warmup_iters);
+        let input = setup();
+        self.bench(label, || func(&input));
+    }
+
+    /// Runs the benchmark. `routine` is called with a sample size, runs that many iterations of
+    /// the benchmarked function and returns the value measured by the stopwatch.
+    /// `empty_routine` does the same for an empty function and measures the loop overhead.
+    fn bench_routine<F, E>(&mut self, label: Label, mut routine: F, mut empty_routine: E)
+    where
+        F: FnMut(usize, &Stopwatch) -> u64,
+        E: FnMut(usize, &Stopwatch) -> u64,
+    {
+        self.config.apply_overrides();
+        let label = self.prepare_label(label);
+        if !self.should_run(&label) {
+            return;
+        }
+        let calibration = self.timer_calibration();
+        let c = &self.config;
+        let overhead = if c.subtract_timer_overhead {
+            calibration.overhead.round() as u64
+        } else {
+            0
+        };
+        let stopwatch = Stopwatch::new(&*c.timer, overhead);
+        #[cfg(feature = "perf")]
+        perf::open(&c.perf_counters);
+        let (warmup_time, warmup_iters) = self.warmup(&mut routine, &stopwatch);
+        let sampling_mode = ActualSamplingMode::decide_mode(c, warmup_time, warmup_iters);
         let mut samples = Vec::with_capacity(c.sample_count);
 
+        // discard the allocations and counts of the warmup
+        alloc::take_counts();
+        #[cfg(feature = "perf")]
+        perf::take_counts();
+        stopwatch.measurements.set(0);
         let bench_time_start = Instant::now();
         for sample_size in sampling_mode.sample_sizes(&self.config) {
-            let mut returns: Vec<R> = Vec::with_capacity(sample_size);
-            // pre-fault vec to reduce overhead of memory allocations during extend call
-            pre_fault_vec(&mut returns);
-            let sample_start = Instant::now();
-            returns.extend((0..sample_size).map(|_| black_box(func())));
-            let sample_duration = sample_start.elapsed();
-            let sample = Sample::from_duration(sample_duration, sample_size);
+            let sample_value = routine(sample_size, &stopwatch);
+            let sample = Sample::from_measurement(sample_value, sample_size);
             samples.push(sample);
         }
         let bench_time = bench_time_start.elapsed();
+        let iters = samples.iter().map(|s| s.sample_size).sum();
+        let measurements = stopwatch.measurements.get();
+        #[cfg(feature = "perf")]
+        let perf_stats = perf::take_counts().map(|counts| PerfStats::new(counts, iters));
+        #[cfg(feature = "perf")]
+        perf::close();
+        let alloc_stats = alloc::is_installed().then(|| {
+            let counts = alloc::take_counts();
+            // the peak memory usage is only meaningful for a single iteration
+            routine(1, &stopwatch);
+            AllocStats::new(counts, iters, alloc::take_counts())
+        });
+        let loop_overhead = c.subtract_loop_overhead.then(|| {
+            let overhead = measure_loop_overhead(&mut empty_routine, &samples, &stopwatch);
+            for sample in &mut samples {
+                sample.subtract_overhead(overhead);
+            }
+            overhead
+        });
 
-        let mut res = BenchResult::from_samples(label, &samples);
+        let mut res = BenchResult::from_samples(label, &samples, sampling_mode, c);
         if let Some(throughput) = c.throughput {
             res = res.with_throughput(throughput);
         }
+        if let Some(loop_overhead) = loop_overhead {
+            res = res.with_loop_overhead(loop_overhead);
+        }
+        if let Some(alloc_stats) = alloc_stats {
+            res = res.with_alloc_stats(alloc_stats);
+        }
+        #[cfg(feature = "perf")]
+        if let Some(perf_stats) = perf_stats {
+            res = res.with_perf_stats(perf_stats);
+        }
         eprintln!(
             "{res}\t\tTotal Time: {:.2}",
             scale_nanos(bench_time.as_nanos() as f64)
         );
+        // Iterations below the resolution are only a problem if they are measured individually,
+        // e.g. with `BatchSize::PerIteration`, instead of many per measurement.
+        let iters_per_measurement = iters as f64 / measurements.max(1) as f64;
+        if res.mean * iters_per_measurement < calibration.resolution as f64 {
+            eprintln!(
+                "warning: the mean iteration time is below the timer resolution of {:.2}, \
+                 consider measuring more work per iteration",
+                res.unit.scale(calibration.resolution as f64)
+            );
+        }
+        if let Some(reg) = res.regression
+            && reg.is_poor_fit()
+        {
+            eprintln!(
+                "warning: the linear regression fits the samples poorly (R² {:.4} < {}), so the \
+                 slope and intercept are unreliable",
+                reg.r_squared,
+                Regression::POOR_FIT_R_SQUARED
+            );
+        }
+        if let Some(baseline) = &c.baseline {
+            match baseline.compare(&res, c) {
+                Ok(comparison) => eprintln!("{comparison}"),
+                Err(err) => eprintln!("{err}"),
+            }
+        }
 
         self.results.push(res);
     }
@@ -98,27 +367,223 @@ impl<'a> Haste<'a> {
         self.bench(label, func);
     }
 
-    pub(crate) fn warmup<F, R>(&self, func: &mut F) -> (Duration, usize)
+    /// Runs the routine with exponentially increasing sample sizes for the configured warmup
+    /// time. Returns the elapsed wall-clock time and the number of iterations.
+    ///
+    /// The wall-clock time is used independently of the timer, as it determines how long
+    /// collecting the samples takes.
+    pub(crate) fn warmup<F>(&self, routine: &mut F, stopwatch: &Stopwatch) -> (Duration, usize)
     where
-        F: FnMut() -> R,
+        F: FnMut(usize, &Stopwatch) -> u64,
     {
         let warmup_start = Instant::now();
         let mut warmup_iters = 0;
         let mut warmup_sample_size = 1;
         while warmup_start.elapsed() < self.config.warmup {
-            for _ in 0..warmup_sample_size {
-                black_box(func());
-            }
+            routine(warmup_sample_size, stopwatch);
             warmup_iters += warmup_sample_size;
             warmup_sample_size *= 2;
         }
-        let warmup_time = warmup_start.elapsed();
-        (warmup_time, warmup_iters)
+        (warmup_start.elapsed(), warmup_iters)
+    }
+
+    /// Returns the calibration of the configured timer. Each type of timer is calibrated and
+    /// its calibration printed when it is first used, as benchmarks usually create their own
+    /// instance of the timer, e.g. with [`Haste::with_timer`].
+    fn timer_calibration(&mut self) -> Calibration {
+        let timer = &*self.config.timer;
+        // dispatches to the concrete type of the timer, as `Any` is a supertrait of `Timer`
+        let type_id = timer.type_id();
+        let calibrations = &mut self.results.calibrations;
+        if let Some((_, calibration)) = calibrations.iter().find(|(id, _)| *id == type_id) {
+            return *calibration;
+        }
+        let calibration = Calibration::measure(timer);
+        eprintln!("Timer {calibration}");
+        calibrations.push((type_id, calibration));
+        calibration
+    }
+
+    /// Applies the label related configuration to `label`.
+    pub(crate) fn prepare_label(&self, mut label: Label) -> Label {
+        label.set_strip_crate_prefix(self.config.strip_crate_prefix);
+        label
+    }
+
+    /// Whether the benchmark with this label should be run. When only collecting labels, the
+    /// label of a selected benchmark is recorded instead.
+    fn should_run(&mut self, label: &Label) -> bool {
+        if self.preselected.take().is_some_and(|pre| pre == *label) {
+            return true;
+        }
+        if !self.config.filter.is_match(&label.to_string()) {
+            return false;
+        }
+        let idx = self.selected;
+        self.selected += 1;
+        match self.selection {
+            Selection::All => true,
+            Selection::Collect => {
+                self.results.listed.push(label.clone());
+                false
+            }
+            Selection::Only(only) => idx == only,
+        }
+    }
+
+    /// Like [`Haste::should_run`], but for a check before the benchmark is run, e.g. before its
+    /// input is created. The following check of the same label is skipped.
+    pub(crate) fn preselect(&mut self, label: &Label) -> bool {
+        let selected = self.should_run(label);
+        if selected {
+            self.preselected = Some(label.clone());
+        }
+        selected
     }
 
     pub(crate) fn set_config(&mut self, config: Config) {
         self.config = config;
     }
+
+    pub(crate) fn set_selection(&mut self, selection: Selection) {
+        self.selection = selection;
+    }
+}
+
+/// Upper bound for the memory used to store return values with [`DropMode::Outside`]. Samples
+/// whose return values would exceed it are split into chunks which are measured individually.
+const MAX_RETURNS_BYTES: usize = 64 * 1024 * 1024;
+
+/// Runs `iters` iterations of `func` and returns the value measured by `stopwatch`. The return
+/// values are dropped according to `drop_mode`.
+fn run_iters<R, F>(func: &mut F, iters: usize, drop_mode: DropMode, stopwatch: &Stopwatch) -> u64
+where
+    F: FnMut() -> R,
+{
+    match drop_mode {
+        DropMode::Outside => {
+            let chunk_size = match mem::size_of::<R>() {
+                0 => usize::MAX,
+                size => cmp::max(MAX_RETURNS_BYTES / size, 1),
+            };
+            let mut remaining = iters;
+            let mut value = 0;
+            while remaining > 0 {
+                let chunk = cmp::min(chunk_size, remaining);
+                let mut returns: Vec<R> = Vec::with_capacity(chunk);
+                // pre-fault vec to reduce overhead of memory allocations during extend call
+                pre_fault_vec(&mut returns);
+                let chunk_start = stopwatch.start();
+                returns.extend((0..chunk).map(|_| black_box(func())));
+                value += stopwatch.stop(chunk_start);
+                remaining -= chunk;
+            }
+            value
+        }
+        DropMode::Inside => {
+            let start = stopwatch.start();
+            for _ in 0..iters {
+                drop(black_box(func()));
+            }
+            stopwatch.stop(start)
+        }
+        DropMode::Leak => {
+            let start = stopwatch.start();
+            for _ in 0..iters {
+                mem::forget(black_box(func()));
+            }
+            stopwatch.stop(start)
+        }
+    }
+}
+
+/// Runs `sample_size` iterations of `routine`, split into batches of inputs which are created
+/// by `setup` before each batch is measured.
+fn run_batches<I, R, S, F>(
+    setup: &mut S,
+    routine: &mut F,
+    sample_size: usize,
+    batch_size: BatchSize,
+    drop_mode: DropMode,
+    stopwatch: &Stopwatch,
+) -> u64
+where
+    S: FnMut() -> I,
+    F: FnMut(I) -> R,
+{
+    let iters_per_batch = batch_size.iters_per_batch(sample_size);
+    let mut remaining = sample_size;
+    let mut sample_value = 0;
+    while remaining > 0 {
+        let batch = cmp::min(iters_per_batch, remaining);
+        let mut inputs = (0..batch).map(|_| setup()).collect::<Vec<I>>().into_iter();
+        let mut func = || {
+            let input = inputs.next().expect("batch has one input per iteration");
+            routine(black_box(input))
+        };
+        sample_value += run_iters(&mut func, batch, drop_mode, stopwatch);
+        remaining -= batch;
+    }
+    sample_value
+}
+
+/// Returns the median iteration time of `empty_routine` with the same sample sizes as
+/// `samples`.
+fn measure_loop_overhead<E>(empty_routine: &mut E, samples: &[Sample], stopwatch: &Stopwatch) -> f64
+where
+    E: FnMut(usize, &Stopwatch) -> u64,
+{
+    let mut iter_times: Vec<f64> = samples
+        .iter()
+        .map(|sample| {
+            let value = empty_routine(sample.sample_size, stopwatch);
+            Sample::from_measurement(value, sample.sample_size).iter_time
+        })
+        .collect();
+    // the empty function must not count towards the allocations of the benchmark
+    alloc::take_counts();
+    stats::sort(&mut iter_times);
+    stats::percentile_of_sorted(&iter_times, 50.0)
+}
+
+/// Measures the benchmarked function with a timer, including the counting of allocations and
+/// hardware counters.
+pub(crate) struct Stopwatch<'a> {
+    timer: &'a dyn Timer,
+    /// Subtracted from every measurement.
+    overhead: u64,
+    /// The number of measurements taken.
+    measurements: Cell<usize>,
+}
+
+impl<'a> Stopwatch<'a> {
+    pub(crate) fn new(timer: &'a dyn Timer, overhead: u64) -> Self {
+        Self {
+            timer,
+            overhead,
+            measurements: Cell::new(0),
+        }
+    }
+
+    /// Starts a measurement.
+    #[inline(always)]
+    fn start(&self) -> u64 {
+        alloc::start_recording();
+        #[cfg(feature = "perf")]
+        perf::enable();
+        self.timer.read()
+    }
+
+    /// Stops the measurement started at `start` and returns the measured value.
+    #[inline(always)]
+    fn stop(&self, start: u64) -> u64 {
+        let end = self.timer.read();
+        #[cfg(feature = "perf")]
+        perf::disable();
+        alloc::stop_recording();
+        self.measurements.set(self.measurements.get() + 1);
+        end.saturating_sub(start).saturating_sub(self.overhead)
+    }
 }
 
 fn pre_fault_vec<T>(v: &mut Vec<T>) {
@@ -141,9 +606,19 @@ fn pre_fault_vec<T>(v: &mut Vec<T>) {
 
 #[cfg(test)]
 mod tests {
-    use std::num::NonZero;
+    use std::{
+        cell::Cell,
+        num::NonZero,
+        sync::atomic::{AtomicU64, Ordering::Relaxed},
+        time::Duration,
+    };
 
-    use crate::bencher::pre_fault_vec;
+    use crate::{
+        BatchSize, Haste, Results,
+        bencher::{Selection, Stopwatch, pre_fault_vec, run_batches, run_iters},
+        drop_mode::DropMode,
+        timer::{Timer, Unit, WallTime},
+    };
 
     // Should be executed under miri
     #[test]
@@ -153,4 +628,133 @@ mod tests {
         assert_eq!(v.len(), 0);
         assert_eq!(v.capacity(), 10000);
     }
+
+    #[test]
+    fn test_run_iters_drop_modes() {
+        struct CountDrop<'a>(&'a Cell<usize>);
+        impl Drop for CountDrop<'_> {
+            fn drop(&mut self) {
+                self.0.set(self.0.get() + 1);
+            }
+        }
+
+        for (drop_mode, expected_drops) in [
+            (DropMode::Outside, 10),
+            (DropMode::Inside, 10),
+            (DropMode::Leak, 0),
+        ] {
+            let drops = Cell::new(0);
+            let timer = WallTime::new();
+            let stopwatch = Stopwatch::new(&timer, 0);
+            run_iters(&mut || CountDrop(&drops), 10, drop_mode, &stopwatch);
+            assert_eq!(drops.get(), expected_drops, "{drop_mode:?}");
+        }
+    }
+
+    #[test]
+    fn test_run_batches() {
+        for (batch_size, expected_batches) in [
+            (BatchSize::SmallInput, 9),
+            (BatchSize::LargeInput, 25),
+            (BatchSize::PerIteration, 25),
+        ] {
+            let mut created = 0;
+            let mut consumed = Vec::new();
+            let timer = WallTime::new();
+            let stopwatch = Stopwatch::new(&timer, 0);
+            run_batches(
+                &mut || {
+                    created += 1;
+                    created
+                },
+                &mut |input| consumed.push(input),
+                25,
+                batch_size,
+                DropMode::Outside,
+                &stopwatch,
+            );
+            assert_eq!(created, 25, "{batch_size:?}");
+            assert_eq!(consumed, (1..=25).collect::<Vec<_>>(), "{batch_size:?}");
+            assert_eq!(
+                stopwatch.measurements.get(),
+                expected_batches,
+                "{batch_size:?}"
+            );
+        }
+    }
+
+    #[test]
+    fn test_collect_does_not_run_setup() {
+        let mut results = Results::default();
+        let mut haste = Haste::new(&mut results);
+        haste.set_selection(Selection::Collect);
+        haste.bench_with_input("with_input", || -> u8 { panic!("setup was run") }, |_| ());
+        haste.bench_batched(
+            "batched",
+            || -> u8 { panic!("setup was run") },
+            |_| (),
+            BatchSize::SmallInput,
+        );
+        let labels: Vec<_> = results.listed.iter().map(|l| l.to_string()).collect();
+        assert_eq!(labels, ["with_input", "batched"]);
+        assert!(results.is_empty());
+    }
+
+    #[test]
+    #[cfg_attr(miri, ignore)]
+    fn test_timer_calibrated_once_per_type() {
+        #[derive(Debug, Default)]
+        struct Ticks(AtomicU64);
+        impl Timer for Ticks {
+            fn read(&self) -> u64 {
+                self.0.fetch_add(1, Relaxed)
+            }
+            fn unit(&self) -> Unit {
+                Unit::Cycles
+            }
+        }
+
+        let mut results = Results::default();
+        let mut haste = Haste::new(&mut results);
+        for _ in 0..2 {
+            haste.with_timer(WallTime::new()).timer_calibration();
+        }
+        assert_eq!(haste.results.calibrations.len(), 1);
+        let calibration = haste.with_timer(Ticks::default()).timer_calibration();
+        assert_eq!(calibration.resolution, 1);
+        assert_eq!(haste.results.calibrations.len(), 2);
+    }
+
+    #[test]
+    #[cfg_attr(miri, ignore)]
+    fn test_loop_overhead_reported() {
+        let mut results = Results::default();
+        let mut haste = Haste::new(&mut results);
+        haste
+            .with_warmup(Duration::from_millis(1))
+            .with_sample_time(Duration::from_millis(1))
+            .with_sample_count(10)
+            .with_bootstrap_resamples(100);
+        haste.bench("without_overhead", || 1);
+        haste.with_loop_overhead_subtracted(true);
+        haste.bench("with_overhead", || 1);
+        haste.bench_batched("batched", || 1, |x| x + 1, BatchSize::SmallInput);
+
+        assert_eq!(
+            results.get("without_overhead").unwrap().loop_overhead(),
+            None
+        );
+        for label in ["with_overhead", "batched"] {
+            let res = results.get(label).unwrap();
+            assert!(res.loop_overhead().is_some_and(|overhead| overhead >= 0.0));
+            assert!(res.samples().iter().all(|s| s.iter_time() >= 0.0));
+        }
+    }
+
+    #[test]
+    #[should_panic(expected = "sample count must be positive")]
+    fn test_zero_sample_count() {
+        let mut results = Results::default();
+        Haste::new(&mut results).with_sample_count(0);
+    }
 }
@@ -1,11 +1,191 @@
-use clap::Parser;
-use regex_lite::Regex;
+use std::{path::PathBuf, time::Duration};
 
-#[derive(Parser)]
+use clap::{Parser, ValueEnum};
+
+use crate::output::OutputFormat;
+
+#[derive(Debug, Default, Parser)]
 /// The haste benchmark runner.
 pub(crate) struct Cli {
-    /// Filter your benchmarks with the provided regex.
-    pub(crate) filter: Option<Regex>,
+    /// Filter your benchmarks with the provided regex, or the exact label with `--exact`.
+    pub(crate) filter: Option<String>,
+    /// Only run the benchmark whose label is exactly the filter. Also applies to `--skip`.
+    #[clap(long)]
+    pub(crate) exact: bool,
+    /// Skip benchmarks whose label contains the provided string. Can be repeated.
+    #[clap(long, value_name = "FILTER")]
+    pub(crate) skip: Vec<String>,
+    /// Only run benchmarks marked with `ignore`.
+    #[clap(long, conflicts_with = "include_ignored")]
+    pub(crate) ignored: bool,
+    /// Run benchmarks marked with `ignore` in addition to the others.
+    #[clap(long)]
+    pub(crate) include_ignored: bool,
+    /// Warm up each benchmark for this long, e.g. `300ms` or `5s`. Overrides the value set in
+    /// code.
+    #[clap(long, value_name = "DURATION", value_parser = parse_duration)]
+    pub(crate) warmup_time: Option<Duration>,
+    /// Target time for collecting all samples of a benchmark, e.g. `300ms` or `5s`. Overrides
+    /// the value set in code.
+    #[clap(long, value_name = "DURATION", value_parser = parse_duration)]
+    pub(crate) measurement_time: Option<Duration>,
+    /// The timer which measures the benchmarks. Overrides the timer set in code.
+    #[clap(long, value_name = "TIMER")]
+    pub(crate) timer: Option<TimerKind>,
+    /// Subtract the calibrated overhead of reading the timer from each measurement.
+    #[clap(long)]
+    pub(crate) subtract_timer_overhead: bool,
+    /// Subtract the overhead of the benchmark loop, measured by benchmarking an empty function,
+    /// from the iteration times.
+    #[clap(long)]
+    pub(crate) subtract_loop_overhead: bool,
+    /// Number of samples to collect per benchmark. Overrides the value set in code.
+    #[clap(long, value_name = "COUNT", value_parser = parse_sample_count)]
+    pub(crate) sample_count: Option<usize>,
+    /// Save the results as a baseline with the provided name in `target/haste/<name>`.
+    #[clap(long, value_name = "NAME", value_parser = parse_baseline_name)]
+    pub(crate) save_baseline: Option<String>,
+    /// Compare the results against the previously saved baseline with the provided name.
+    #[clap(long, value_name = "NAME", value_parser = parse_baseline_name)]
+    pub(crate) baseline: Option<String>,
+    /// Write all results in a machine-readable format to stdout or the `--output` file.
+    #[clap(long, value_name = "FORMAT")]
+    pub(crate) output_format: Option<OutputFormat>,
+    /// Write the `--output-format` results to this file instead of stdout.
+    #[clap(long, value_name = "PATH", requires = "output_format")]
+    pub(crate) output: Option<PathBuf>,
+    /// List the labels of all selected benchmarks without running them.
+    #[clap(long)]
+    pub(crate) list: bool,
+    /// The format of `--list`.
+    #[clap(long, value_name = "FORMAT", default_value = "pretty")]
+    pub(crate) format: ListFormat,
+    /// Run the benchmarks in a random order instead of sorted by label. The seed is printed so
+    /// the order can be reproduced with `--shuffle-seed`.
+    #[clap(long)]
+    pub(crate) shuffle: bool,
+    /// Run the benchmarks in a random order determined by the seed.
+    #[clap(long, value_name = "SEED")]
+    pub(crate) shuffle_seed: Option<u64>,
     #[clap(long, hide(true))]
     bench: bool,
 }
+
+/// The built-in timers, see [`crate::timer`].
+#[derive(Clone, Copy, Debug, ValueEnum)]
+pub(crate) enum TimerKind {
+    /// Wall-clock time.
+    Wall,
+    /// Reference cycles of the time stamp counter (x86_64 only).
+    Tsc,
+    /// CPU time of the benchmark thread (Unix only).
+    ThreadCpu,
+    /// CPU time of the whole process (Unix only).
+    ProcessCpu,
+}
+
+/// Output formats of `--list`, compatible with the libtest harness.
+#[derive(Clone, Copy, Debug, Default, ValueEnum)]
+pub(crate) enum ListFormat {
+    /// One `<label>: benchmark` line per benchmark followed by the summary line of libtest,
+    /// e.g. `0 tests, 2 benchmarks`.
+    #[default]
+    Pretty,
+    /// Only the `<label>: benchmark` lines.
+    Terse,
+}
+
+/// The summary line of `--list --format pretty` as printed by libtest.
+pub(crate) fn list_summary(benchmarks: usize) -> String {
+    let plural = |count: usize, noun: &str| match count {
+        1 => format!("1 {noun}"),
+        count => format!("{count} {noun}s"),
+    };
+    format!("{}, {}", plural(0, "test"), plural(benchmarks, "benchmark"))
+}
+
+fn parse_baseline_name(name: &str) -> Result<String, String> {
+    if name.is_empty() || name.contains(['/', '\\']) || name == "." || name == ".." {
+        return Err(format!("invalid baseline name `{name}`"));
+    }
+    Ok(name.to_string())
+}
+
+/// Parses a duration with a unit suffix, e.g. `300ms`, `1.5s` or `2m`.
+fn parse_duration(s: &str) -> Result<Duration, String> {
+    let s = s.trim();
+    let unit_start = s
+        .find(|c: char| !(c.is_ascii_digit() || c == '.'))
+        .ok_or_else(|| format!("missing unit in `{s}`, e.g. `{s}ms` or `{s}s`"))?;
+    let (value, unit) = s.split_at(unit_start);
+    let value: f64 = value
+        .parse()
+        .map_err(|_| format!("invalid duration `{s}`"))?;
+    let secs_per_unit = match unit.trim() {
+        "ns" => 1e-9,
+        "us" | "µs" => 1e-6,
+        "ms" => 1e-3,
+        "s" => 1.0,
+        "m" | "min" => 60.0,
+        unit => return Err(format!("unknown duration unit `{unit}`")),
+    };
+    Duration::try_from_secs_f64(value * secs_per_unit).map_err(|err| err.to_string())
+}
+
+fn parse_sample_count(s: &str) -> Result<usize, String> {
+    match s.parse() {
+        Ok(0) => Err("sample count must be positive".to_string()),
+        Ok(count) => Ok(count),
+        Err(err) => Err(format!("invalid sample count `{s}`: {err}")),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use clap::Parser;
+
+    use super::{Cli, ListFormat, list_summary, parse_baseline_name, parse_duration};
+
+    #[test]
+    fn test_list_summary() {
+        assert_eq!(list_summary(0), "0 tests, 0 benchmarks");
+        assert_eq!(list_summary(1), "0 tests, 1 benchmark");
+        assert_eq!(list_summary(40), "0 tests, 40 benchmarks");
+    }
+
+    #[test]
+    fn test_parse_baseline_name() {
+        assert_eq!(parse_baseline_name("main"), Ok("main".to_string()));
+        assert_eq!(parse_baseline_name("v1.2"), Ok("v1.2".to_string()));
+        assert!(parse_baseline_name("").is_err());
+        assert!(parse_baseline_name(".").is_err());
+        assert!(parse_baseline_name("..").is_err());
+        assert!(parse_baseline_name("a/b").is_err());
+        assert!(parse_baseline_name("a\\b").is_err());
+    }
+
+    #[test]
+    fn test_parse_duration() {
+        assert_eq!(parse_duration("300ms"), Ok(Duration::from_millis(300)));
+        assert_eq!(parse_duration("5s"), Ok(Duration::from_secs(5)));
+        assert_eq!(parse_duration("1.5s"), Ok(Duration::from_millis(1500)));
+        assert_eq!(parse_duration("250µs"), Ok(Duration::from_micros(250)));
+        assert_eq!(parse_duration("2m"), Ok(Duration::from_secs(120)));
+        assert!(parse_duration("5").is_err());
+        assert!(parse_duration("5h").is_err());
+        assert!(parse_duration("ms").is_err());
+    }
+
+    #[test]
+    fn test_parse_list_exact() {
+        let cli = Cli::parse_from([
+            "bench", "--list", "--format", "terse", "--exact", "hypot/(3",
+        ]);
+        assert!(cli.list);
+        assert!(matches!(cli.format, ListFormat::Terse));
+        assert!(cli.exact);
+        assert_eq!(cli.filter.as_deref(), Some("hypot/(3"));
+    }
+}
@@ -1,16 +1,75 @@
-use std::time::Duration;
+use std::{sync::Arc, time::Duration};
 
-use regex_lite::Regex;
+#[cfg(feature = "perf")]
+use crate::perf::PerfCounter;
+use crate::{
+    baseline::Baseline,
+    drop_mode::DropMode,
+    filter::Filter,
+    sampling_mode::SamplingMode,
+    throughput::Throughput,
+    timer::{Timer, WallTime},
+};
 
-use crate::throughput::Throughput;
-
-#[derive(Clone)]
+#[derive(Clone, Debug)]
 pub(crate) struct Config {
-    pub(crate) filter: Option<Regex>,
+    pub(crate) filter: Filter,
     pub(crate) warmup: Duration,
     pub(crate) sample_time: Duration,
     pub(crate) sample_count: usize,
+    pub(crate) sampling_mode: SamplingMode,
+    pub(crate) drop_mode: DropMode,
+    pub(crate) timer: Arc<dyn Timer>,
+    /// Whether the calibrated overhead of the timer is subtracted from each measurement.
+    pub(crate) subtract_timer_overhead: bool,
+    /// Whether the overhead of the benchmark loop, measured with an empty function, is
+    /// subtracted from the iteration times.
+    pub(crate) subtract_loop_overhead: bool,
     pub(crate) throughput: Option<Throughput>,
+    pub(crate) confidence_level: f64,
+    pub(crate) bootstrap_resamples: usize,
+    pub(crate) exclude_severe_outliers: bool,
+    pub(crate) baseline: Option<Arc<Baseline>>,
+    pub(crate) noise_threshold: f64,
+    /// Whether the crate name is stripped from the module path in labels.
+    pub(crate) strip_crate_prefix: bool,
+    #[cfg(feature = "perf")]
+    pub(crate) perf_counters: Vec<PerfCounter>,
+    pub(crate) overrides: Overrides,
+}
+
+/// Values which take precedence over the configuration of individual benchmarks, e.g. because
+/// they were provided on the command line.
+#[derive(Clone, Debug, Default)]
+pub(crate) struct Overrides {
+    pub(crate) warmup: Option<Duration>,
+    pub(crate) sample_time: Option<Duration>,
+    pub(crate) sample_count: Option<usize>,
+    pub(crate) timer: Option<Arc<dyn Timer>>,
+    pub(crate) subtract_timer_overhead: Option<bool>,
+    pub(crate) subtract_loop_overhead: Option<bool>,
+}
+
+impl Config {
+    pub(crate) fn apply_overrides(&mut self) {
+        let Overrides {
+            warmup,
+            sample_time,
+            sample_count,
+            ref timer,
+            subtract_timer_overhead,
+            subtract_loop_overhead,
+        } = self.overrides;
+        self.warmup = warmup.unwrap_or(self.warmup);
+        self.sample_time = sample_time.unwrap_or(self.sample_time);
+        self.sample_count = sample_count.unwrap_or(self.sample_count);
+        self.subtract_timer_overhead =
+            subtract_timer_overhead.unwrap_or(self.subtract_timer_overhead);
+        self.subtract_loop_overhead = subtract_loop_overhead.unwrap_or(self.subtract_loop_overhead);
+        if let Some(timer) = timer {
+            self.timer = Arc::clone(timer);
+        }
+    }
 }
 
 impl Default for Config {
@@ -19,8 +78,22 @@ impl Default for Config {
             warmup: Duration::from_millis(500),
             sample_time: Duration::from_secs(2),
             sample_count: 100,
+            sampling_mode: SamplingMode::Auto,
+            drop_mode: DropMode::Outside,
+            timer: Arc::new(WallTime::new()),
+            subtract_timer_overhead: false,
+            subtract_loop_overhead: false,
             throughput: None,
-            filter: None,
+            filter: Filter::default(),
+            confidence_level: 0.95,
+            bootstrap_resamples: 10_000,
+            exclude_severe_outliers: false,
+            baseline: None,
+            noise_threshold: 0.01,
+            strip_crate_prefix: true,
+            #[cfg(feature = "perf")]
+            perf_counters: PerfCounter::DEFAULT.to_vec(),
+            overrides: Overrides::default(),
         }
     }
 }
@@ -0,0 +1,15 @@
+/// Determines how the return values of a benchmarked function are dropped.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum DropMode {
+    /// Return values are collected and dropped after a sample is measured, so the cost of
+    /// dropping them is not measured. If the return values of a sample would use a lot of
+    /// memory, the sample is measured in multiple chunks.
+    #[default]
+    Outside,
+    /// Return values are dropped directly after each call and the cost of dropping them is part
+    /// of the measurement, e.g. to benchmark the allocation and deallocation of a structure.
+    Inside,
+    /// Return values are leaked with [`std::mem::forget`] and never dropped. Memory owned by the
+    /// return values is never freed, so this is only suitable for small or trivial values.
+    Leak,
+}
@@ -0,0 +1,80 @@
+use regex_lite::Regex;
+
+/// Selects the benchmarks to run by their label.
+#[derive(Clone, Debug, Default)]
+pub(crate) struct Filter {
+    /// Only run benchmarks whose label matches this pattern. It is a regex, unless `exact` is
+    /// set.
+    pattern: Option<String>,
+    /// The compiled `pattern`, if it is a regex.
+    regex: Option<Regex>,
+    /// Match the pattern and the skip patterns against the whole label instead of searching
+    /// for them.
+    pub(crate) exact: bool,
+    /// Skip benchmarks whose label contains one of these strings.
+    pub(crate) skip: Vec<String>,
+}
+
+impl Filter {
+    /// Sets the pattern, which is compiled to a regex unless `exact` is set.
+    pub(crate) fn set_pattern(&mut self, pattern: String) -> Result<(), regex_lite::Error> {
+        self.regex = if self.exact {
+            None
+        } else {
+            Some(Regex::new(&pattern)?)
+        };
+        self.pattern = Some(pattern);
+        Ok(())
+    }
+
+    pub(crate) fn pattern(&self) -> Option<&str> {
+        self.pattern.as_deref()
+    }
+
+    pub(crate) fn is_match(&self, label: &str) -> bool {
+        let selected = match (&self.pattern, &self.regex) {
+            (Some(pattern), _) if self.exact => pattern == label,
+            (_, Some(regex)) => regex.is_match(label),
+            _ => true,
+        };
+        let skipped = self.skip.iter().any(|skip| {
+            if self.exact {
+                skip == label
+            } else {
+                label.contains(skip.as_str())
+            }
+        });
+        selected && !skipped
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::Filter;
+
+    #[test]
+    fn test_filter() {
+        let mut filter = Filter {
+            skip: vec!["/5".to_string()],
+            ..Filter::default()
+        };
+        filter.set_pattern("fib".to_string()).unwrap();
+        assert!(filter.is_match("bench_fib/2"));
+        assert!(!filter.is_match("bench_fib/5"));
+        assert!(!filter.is_match("bench_add/2"));
+
+        let mut filter = Filter {
+            exact: true,
+            ..Filter::default()
+        };
+        filter.set_pattern("bench_fib/2".to_string()).unwrap();
+        assert!(filter.is_match("bench_fib/2"));
+        assert!(!filter.is_match("bench_fib/20"));
+
+        // exact patterns are not compiled as regex
+        filter.set_pattern("hypot/(3, 4)".to_string()).unwrap();
+        assert!(filter.is_match("hypot/(3, 4)"));
+        filter.exact = false;
+        assert!(filter.set_pattern("hypot/(3".to_string()).is_err());
+    }
+}
@@ -1,21 +1,103 @@
-use std::fmt::{Display, Write};
+use std::fmt::{Debug, Display, Write};
 
-#[derive(Debug)]
+/// The name of a benchmark.
+///
+/// A label consists of an optional path, which is either a group or the module path of the
+/// benchmark, and parts which are separated by `/`, e.g. `parser::json::parse/small`. By default
+/// the crate name is stripped from the module path (see [`Runner::strip_crate_prefix`]).
+///
+/// [`Runner::strip_crate_prefix`]: crate::Runner::strip_crate_prefix
+#[derive(Clone, Debug, PartialEq, Eq)]
 pub struct Label {
+    module_path: Option<String>,
+    group: Option<String>,
+    strip_crate_prefix: bool,
     parts: Vec<String>,
 }
 
 impl Label {
     pub fn new(root: &str) -> Self {
         Self {
+            module_path: None,
+            group: None,
+            strip_crate_prefix: true,
             parts: vec![root.to_string()],
         }
     }
 
+    /// Sets the module path of the benchmark, usually `module_path!()`.
+    pub fn with_module_path(mut self, module_path: &str) -> Self {
+        self.module_path = Some(module_path.to_string());
+        self
+    }
+
+    /// Sets the group of the benchmark, which replaces the module path in the label.
+    pub fn with_group(mut self, group: &str) -> Self {
+        self.group = Some(group.to_string());
+        self
+    }
+
     pub fn with_part(mut self, part: impl Display) -> Self {
         self.parts.push(part.to_string());
         self
     }
+
+    /// The parts of the label, starting with the root.
+    pub fn parts(&self) -> &[String] {
+        &self.parts
+    }
+
+    pub fn module_path(&self) -> Option<&str> {
+        self.module_path.as_deref()
+    }
+
+    pub fn group(&self) -> Option<&str> {
+        self.group.as_deref()
+    }
+
+    /// The path which is displayed in front of the parts. This is the group if set, or the
+    /// module path, optionally without the crate name.
+    pub fn path(&self) -> &str {
+        if let Some(group) = &self.group {
+            return group;
+        }
+        match &self.module_path {
+            Some(module_path) if self.strip_crate_prefix => module_path
+                .split_once("::")
+                .map_or("", |(_crate_name, rest)| rest),
+            Some(module_path) => module_path,
+            None => "",
+        }
+    }
+
+    /// Whether the label is displayed as `label`, without formatting it.
+    pub(crate) fn displays_as(&self, label: &str) -> bool {
+        let path = self.path();
+        let mut rest = label;
+        if !path.is_empty() {
+            match rest.strip_prefix(path).and_then(|r| r.strip_prefix("::")) {
+                Some(r) => rest = r,
+                None => return false,
+            }
+        }
+        for (idx, part) in self.parts.iter().enumerate() {
+            if idx > 0 {
+                match rest.strip_prefix('/') {
+                    Some(r) => rest = r,
+                    None => return false,
+                }
+            }
+            match rest.strip_prefix(part.as_str()) {
+                Some(r) => rest = r,
+                None => return false,
+            }
+        }
+        rest.is_empty()
+    }
+
+    pub(crate) fn set_strip_crate_prefix(&mut self, strip: bool) {
+        self.strip_crate_prefix = strip;
+    }
 }
 
 impl<'a> From<&'a str> for Label {
@@ -26,6 +108,11 @@ impl<'a> From<&'a str> for Label {
 
 impl Display for Label {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let path = self.path();
+        if !path.is_empty() {
+            f.write_str(path)?;
+            f.write_str("::")?;
+        }
         if let Some((first, rest)) = self.parts.split_first() {
             f.write_str(first)?;
 
@@ -38,3 +125,83 @@ impl Display for Label {
         Ok(())
     }
 }
+
+/// Wrapper to format benchmark args as label parts. Uses the `Display` impl of the arg if
+/// it has one and falls back to `Debug` otherwise (via autoref specialization). Called as
+/// `(&LabelPart(&arg)).label_part()` by the `#[bench]` macro.
+#[doc(hidden)]
+pub struct LabelPart<'a, T: ?Sized>(pub &'a T);
+
+#[doc(hidden)]
+pub trait DisplayLabelPart {
+    fn label_part(&self) -> String;
+}
+
+impl<T: Display + ?Sized> DisplayLabelPart for LabelPart<'_, T> {
+    fn label_part(&self) -> String {
+        self.0.to_string()
+    }
+}
+
+#[doc(hidden)]
+pub trait DebugLabelPart {
+    fn label_part(&self) -> String;
+}
+
+impl<T: Debug + ?Sized> DebugLabelPart for &LabelPart<'_, T> {
+    fn label_part(&self) -> String {
+        format!("{:?}", self.0)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{DebugLabelPart, DisplayLabelPart, Label, LabelPart};
+
+    #[test]
+    fn test_label_path() {
+        let label = Label::new("parse").with_part(1);
+        assert_eq!(label.to_string(), "parse/1");
+
+        let mut label = Label::new("parse")
+            .with_module_path("my_crate::json")
+            .with_part(1);
+        assert_eq!(label.to_string(), "json::parse/1");
+        label.set_strip_crate_prefix(false);
+        assert_eq!(label.to_string(), "my_crate::json::parse/1");
+
+        let label = Label::new("parse").with_module_path("my_crate");
+        assert_eq!(label.to_string(), "parse");
+
+        let label = Label::new("parse")
+            .with_module_path("my_crate::json")
+            .with_group("parsing");
+        assert_eq!(label.to_string(), "parsing::parse");
+    }
+
+    #[test]
+    fn test_displays_as() {
+        let label = Label::new("parse")
+            .with_module_path("my_crate::json")
+            .with_part(1);
+        assert!(label.displays_as("json::parse/1"));
+        assert!(!label.displays_as("json::parse"));
+        assert!(!label.displays_as("json::parse/1/2"));
+        assert!(!label.displays_as("parse/1"));
+        assert!(!label.displays_as("json:parse/1"));
+
+        let label = Label::new("parse");
+        assert!(label.displays_as("parse"));
+        assert!(!label.displays_as("::parse"));
+    }
+
+    // the borrow is needed to select the `Debug` fallback, as in the macro expansion
+    #[allow(clippy::needless_borrow)]
+    #[test]
+    fn test_label_part_fallback() {
+        assert_eq!((&LabelPart(&42)).label_part(), "42");
+        assert_eq!((&LabelPart(&String::from("a b"))).label_part(), "a b");
+        assert_eq!((&LabelPart(&vec![1, 2])).label_part(), "[1, 2]");
+        assert_eq!((&LabelPart(&(1, "x"))).label_part(), "(1, \"x\")");
+    }
+}
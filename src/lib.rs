@@ -1,52 +1,65 @@
-/// A work-in-progress benchmarking library.
+//! A work-in-progress benchmarking library.
+
+pub mod alloc;
+mod baseline;
+mod batch_size;
 mod bench_result;
 mod bencher;
 mod cli;
 mod config;
 #[cfg(all(doctest, feature = "tokio"))]
 mod doctests;
+mod drop_mode;
+mod filter;
 mod label;
+mod output;
+#[cfg(feature = "perf")]
+pub mod perf;
+mod registry;
+mod runner;
 mod sample;
 mod sampling_mode;
+mod stats;
 pub mod throughput;
+pub mod timer;
+
+// lets the `#[bench]` macro be used in the tests of this crate
+#[cfg(test)]
+extern crate self as haste;
 
-use crate::bench_result::Results;
+pub use crate::batch_size::BatchSize;
+pub use crate::bench_result::{BenchResult, Results};
 pub use crate::bencher::Haste;
-use crate::config::Config;
+pub use crate::drop_mode::DropMode;
 pub use crate::label::Label;
-use clap::Parser;
+pub use crate::runner::Runner;
+pub use crate::sample::Sample;
+pub use crate::sampling_mode::SamplingMode;
+pub use crate::stats::{ConfidenceInterval, Outliers, Regression};
 pub use haste_macros::bench;
 pub use throughput::Throughput;
+pub use timer::{Timer, Unit};
 
 /// A main function to call in your benchmark's main.
 ///
 /// Call this function in your `benches/` benchmark main function. This will parse provided
 /// arguments, apply filters, and run the corresponding benchmarks. The available CLI options
 /// can be viewed with `cargo bench -- --help`  (note `--` after bench).
+///
+/// Use a [`Runner`] to change the default configuration or inspect the results.
 pub fn main() {
-    let cli = cli::Cli::parse();
-    let mut results = Results::default();
-    let config = Config {
-        filter: cli.filter,
-        ..Default::default()
-    };
-
-    for bench in __private::BENCHMARKS {
-        let mut haste = Haste::new(&mut results);
-        haste.set_config(config.clone());
-        bench(haste);
-    }
+    Runner::new().parse_args().run();
 }
 
 #[doc(hidden)]
 pub mod __private {
-    use crate::Haste;
-
+    pub use crate::label::{DebugLabelPart, DisplayLabelPart, LabelPart};
+    pub use crate::registry::{Benchmark, bench_module_path, should_run};
     pub use linkme;
     pub use linkme::distributed_slice;
     #[cfg(feature = "tokio")]
     pub use tokio;
 
     #[distributed_slice]
-    pub static BENCHMARKS: [fn(Haste)];
+    pub static BENCHMARKS: [Benchmark];
 }
@@ -0,0 +1,381 @@
+use std::{
+    borrow::Cow,
+    fs::File,
+    io::{self, BufWriter, Write},
+    path::Path,
+};
+
+use clap::ValueEnum;
+use serde::Serialize;
+
+use crate::{
+    bench_result::{BenchResult, Results},
+    sample::Sample,
+    sampling_mode::ActualSamplingMode,
+    stats::ConfidenceInterval,
+    throughput::Throughput,
+    timer::Unit,
+};
+
+/// Version of the JSON output schema. Increment this on breaking changes of the schema.
+pub(crate) const JSON_SCHEMA_VERSION: u32 = 1;
+
+/// Machine-readable output formats of the results.
+#[derive(Clone, Copy, Debug, ValueEnum)]
+pub(crate) enum OutputFormat {
+    /// All results and raw samples as a single JSON document.
+    Json,
+    /// One row per raw sample with the benchmark label and sample index.
+    Csv,
+}
+
+/// Writes the results in the provided format to `path`, or stdout if no path is given.
+pub(crate) fn write_results(
+    results: &Results,
+    format: OutputFormat,
+    path: Option<&Path>,
+) -> io::Result<()> {
+    let mut writer: Box<dyn Write> = match path {
+        Some(path) => Box::new(BufWriter::new(File::create(path)?)),
+        None => Box::new(io::stdout().lock()),
+    };
+    match format {
+        OutputFormat::Json => write_json(results, &mut writer)?,
+        OutputFormat::Csv => write_csv(results, &mut writer)?,
+    }
+    writer.flush()
+}
+
+fn write_json(results: &Results, writer: &mut impl Write) -> io::Result<()> {
+    let output = JsonOutput {
+        schema_version: JSON_SCHEMA_VERSION,
+        benchmarks: results.iter().map(JsonBench::from).collect(),
+    };
+    serde_json::to_writer_pretty(&mut *writer, &output).map_err(io::Error::other)?;
+    writeln!(writer)
+}
+
+fn write_csv(results: &Results, writer: &mut impl Write) -> io::Result<()> {
+    writeln!(writer, "label,sample_index,iter_time,unit,sample_size")?;
+    for res in results.iter() {
+        let label = res.label.to_string();
+        let label = csv_field(&label);
+        for (idx, sample) in res.samples.iter().enumerate() {
+            writeln!(
+                writer,
+                "{label},{idx},{},{},{}",
+                sample.iter_time, res.unit, sample.sample_size
+            )?;
+        }
+    }
+    Ok(())
+}
+
+/// Quotes a CSV field if it contains special characters (RFC 4180).
+fn csv_field(field: &str) -> Cow<'_, str> {
+    if field.contains([',', '"', '\n', '\r']) {
+        Cow::Owned(format!("\"{}\"", field.replace('"', "\"\"")))
+    } else {
+        Cow::Borrowed(field)
+    }
+}
+
+#[derive(Serialize)]
+struct JsonOutput<'a> {
+    schema_version: u32,
+    benchmarks: Vec<JsonBench<'a>>,
+}
+
+#[derive(Serialize)]
+struct JsonBench<'a> {
+    label: String,
+    label_parts: &'a [String],
+    module_path: Option<&'a str>,
+    group: Option<&'a str>,
+    /// The unit of all times in the statistics and samples.
+    unit: Unit,
+    config: JsonConfig,
+    sampling_mode: JsonSamplingMode,
+    throughput: Option<JsonThroughput>,
+    /// The overhead of the benchmark loop per iteration, if it was subtracted from all times.
+    loop_overhead: Option<f64>,
+    alloc: Option<JsonAllocStats>,
+    /// Mean count per iteration of each available hardware counter. Always present so that the
+    /// schema doesn't depend on the `perf` feature, `null` without it.
+    perf: Option<std::collections::BTreeMap<&'static str, f64>>,
+    statistics: JsonStatistics,
+    samples: &'a [Sample],
+}
+
+#[derive(Serialize)]
+struct JsonConfig {
+    warmup_ns: u128,
+    sample_time_ns: u128,
+    sample_count: usize,
+    confidence_level: f64,
+    bootstrap_resamples: usize,
+    exclude_severe_outliers: bool,
+}
+
+#[derive(Serialize)]
+#[serde(tag = "kind", rename_all = "snake_case")]
+enum JsonSamplingMode {
+    Linear { sampling_factor: usize },
+    Flat { sample_size: usize },
+}
+
+#[derive(Serialize)]
+#[serde(rename_all = "snake_case")]
+enum JsonThroughput {
+    Bytes(usize),
+    Items(usize),
+}
+
+#[derive(Serialize)]
+struct JsonAllocStats {
+    allocs: f64,
+    deallocs: f64,
+    bytes: f64,
+    peak_bytes: u64,
+}
+
+#[derive(Serialize)]
+struct JsonStatistics {
+    min: f64,
+    max: f64,
+    mean: f64,
+    median: f64,
+    mean_ci: JsonConfidenceInterval,
+    median_ci: JsonConfidenceInterval,
+    regression: Option<JsonRegression>,
+    outliers: JsonOutliers,
+}
+
+#[derive(Serialize)]
+struct JsonConfidenceInterval {
+    lower: f64,
+    upper: f64,
+    confidence_level: f64,
+}
+
+#[derive(Serialize)]
+struct JsonRegression {
+    slope: f64,
+    intercept: f64,
+    r_squared: f64,
+}
+
+#[derive(Serialize)]
+struct JsonOutliers {
+    low_severe: usize,
+    low_mild: usize,
+    high_mild: usize,
+    high_severe: usize,
+    severe_excluded: bool,
+}
+
+impl<'a> From<&'a BenchResult> for JsonBench<'a> {
+    fn from(res: &'a BenchResult) -> Self {
+        let c = &res.config;
+        Self {
+            label: res.label.to_string(),
+            label_parts: res.label.parts(),
+            module_path: res.label.module_path(),
+            group: res.label.group(),
+            unit: res.unit,
+            config: JsonConfig {
+                warmup_ns: c.warmup.as_nanos(),
+                sample_time_ns: c.sample_time.as_nanos(),
+                sample_count: c.sample_count,
+                confidence_level: c.confidence_level,
+                bootstrap_resamples: c.bootstrap_resamples,
+                exclude_severe_outliers: c.exclude_severe_outliers,
+            },
+            sampling_mode: match res.sampling_mode {
+                ActualSamplingMode::Linear { sampling_factor } => {
+                    JsonSamplingMode::Linear { sampling_factor }
+                }
+                ActualSamplingMode::Flat { sample_size } => JsonSamplingMode::Flat { sample_size },
+            },
+            throughput: res.throughput.map(|throughput| match throughput {
+                Throughput::Bytes(bytes) => JsonThroughput::Bytes(bytes),
+                Throughput::Items(items) => JsonThroughput::Items(items),
+            }),
+            loop_overhead: res.loop_overhead,
+            alloc: res.alloc_stats.map(|alloc| JsonAllocStats {
+                allocs: alloc.allocs,
+                deallocs: alloc.deallocs,
+                bytes: alloc.bytes,
+                peak_bytes: alloc.peak_bytes,
+            }),
+            #[cfg(feature = "perf")]
+            perf: res.perf_stats.as_ref().map(|perf| {
+                perf.iter()
+                    .map(|(counter, count)| (counter.name(), count))
+                    .collect()
+            }),
+            #[cfg(not(feature = "perf"))]
+            perf: None,
+            statistics: JsonStatistics {
+                min: res.min,
+                max: res.max,
+                mean: res.mean,
+                median: res.median,
+                mean_ci: res.mean_ci.into(),
+                median_ci: res.median_ci.into(),
+                regression: res.regression.map(|reg| JsonRegression {
+                    slope: reg.slope,
+                    intercept: reg.intercept,
+                    r_squared: reg.r_squared,
+                }),
+                outliers: JsonOutliers {
+                    low_severe: res.outliers.low_severe,
+                    low_mild: res.outliers.low_mild,
+                    high_mild: res.outliers.high_mild,
+                    high_severe: res.outliers.high_severe,
+                    severe_excluded: res.severe_outliers_excluded,
+                },
+            },
+            samples: &res.samples,
+        }
+    }
+}
+
+impl From<ConfidenceInterval> for JsonConfidenceInterval {
+    fn from(ci: ConfidenceInterval) -> Self {
+        Self {
+            lower: ci.lower,
+            upper: ci.upper,
+            confidence_level: ci.confidence_level,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::Value;
+
+    use super::{JSON_SCHEMA_VERSION, csv_field, write_json};
+    use crate::{
+        bench_result::{BenchResult, Results},
+        config::Config,
+        sample::Sample,
+        sampling_mode::ActualSamplingMode,
+    };
+
+    fn keys(value: &Value) -> Vec<&str> {
+        let mut keys: Vec<&str> = value
+            .as_object()
+            .expect("object")
+            .keys()
+            .map(String::as_str)
+            .collect();
+        keys.sort_unstable();
+        keys
+    }
+
+    #[test]
+    fn test_json_schema() {
+        let config = Config {
+            bootstrap_resamples: 100,
+            ..Config::default()
+        };
+        let samples: Vec<Sample> = (1..=10)
+            .map(|sample_size| Sample {
+                iter_time: 100.0 + sample_size as f64,
+                sample_size,
+            })
+            .collect();
+        let sampling_mode = ActualSamplingMode::Linear { sampling_factor: 1 };
+        let mut results = Results::default();
+        results.push(BenchResult::from_samples(
+            "fib/10".into(),
+            &samples,
+            sampling_mode,
+            &config,
+        ));
+        let mut json = Vec::new();
+        write_json(&results, &mut json).unwrap();
+        let json: Value = serde_json::from_slice(&json).unwrap();
+
+        assert_eq!(JSON_SCHEMA_VERSION, 1);
+        assert_eq!(keys(&json), ["benchmarks", "schema_version"]);
+        assert_eq!(json["schema_version"], 1);
+        let bench = &json["benchmarks"][0];
+        assert_eq!(
+            keys(bench),
+            [
+                "alloc",
+                "config",
+                "group",
+                "label",
+                "label_parts",
+                "loop_overhead",
+                "module_path",
+                "perf",
+                "samples",
+                "sampling_mode",
+                "statistics",
+                "throughput",
+                "unit",
+            ]
+        );
+        assert_eq!(bench["label"], "fib/10");
+        assert_eq!(bench["unit"], "nanoseconds");
+        assert_eq!(bench["perf"], Value::Null);
+        assert_eq!(
+            keys(&bench["config"]),
+            [
+                "bootstrap_resamples",
+                "confidence_level",
+                "exclude_severe_outliers",
+                "sample_count",
+                "sample_time_ns",
+                "warmup_ns",
+            ]
+        );
+        assert_eq!(bench["sampling_mode"]["kind"], "linear");
+        assert_eq!(bench["sampling_mode"]["sampling_factor"], 1);
+        let stats = &bench["statistics"];
+        assert_eq!(
+            keys(stats),
+            [
+                "max",
+                "mean",
+                "mean_ci",
+                "median",
+                "median_ci",
+                "min",
+                "outliers",
+                "regression",
+            ]
+        );
+        assert_eq!(
+            keys(&stats["mean_ci"]),
+            ["confidence_level", "lower", "upper"]
+        );
+        assert_eq!(
+            keys(&stats["regression"]),
+            ["intercept", "r_squared", "slope"]
+        );
+        assert_eq!(
+            keys(&stats["outliers"]),
+            [
+                "high_mild",
+                "high_severe",
+                "low_mild",
+                "low_severe",
+                "severe_excluded",
+            ]
+        );
+        assert_eq!(keys(&bench["samples"][0]), ["iter_time", "sample_size"]);
+        assert_eq!(bench["samples"][0]["iter_time"], 101.0);
+    }
+
+    #[test]
+    fn test_csv_field() {
+        assert_eq!(csv_field("bench_fib/2"), "bench_fib/2");
+        assert_eq!(csv_field("sort/1,2"), "\"sort/1,2\"");
+        assert_eq!(csv_field("say \"hi\""), "\"say \"\"hi\"\"\"");
+    }
+}
@@ -0,0 +1,390 @@
+//! Hardware performance counters via Linux `perf_event_open`.
+//!
+//! With the `perf` feature enabled, the counters configured with
+//! [`Haste::with_perf_counters`](crate::Haste::with_perf_counters) are read around each sample
+//! and reported per iteration in the [`BenchResult`](crate::BenchResult). The counters only count
+//! user space events of the benchmark thread.
+//!
+//! If counters are unavailable, e.g. on other operating systems, because of the
+//! `perf_event_paranoid` setting or in containers, a warning is printed and the benchmarks are
+//! run without them.
+use std::{
+    fmt::Display,
+    sync::atomic::{AtomicBool, Ordering::Relaxed},
+};
+
+/// A hardware event which can be counted.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum PerfCounter {
+    Instructions,
+    Cycles,
+    CacheReferences,
+    CacheMisses,
+    BranchInstructions,
+    BranchMisses,
+}
+
+impl PerfCounter {
+    /// The counters used by default.
+    pub(crate) const DEFAULT: [PerfCounter; 4] = [
+        PerfCounter::Instructions,
+        PerfCounter::Cycles,
+        PerfCounter::CacheMisses,
+        PerfCounter::BranchMisses,
+    ];
+
+    /// The name of the counter in snake case, e.g. `cache_misses`.
+    pub fn name(&self) -> &'static str {
+        match self {
+            PerfCounter::Instructions => "instructions",
+            PerfCounter::Cycles => "cycles",
+            PerfCounter::CacheReferences => "cache_references",
+            PerfCounter::CacheMisses => "cache_misses",
+            PerfCounter::BranchInstructions => "branch_instructions",
+            PerfCounter::BranchMisses => "branch_misses",
+        }
+    }
+}
+
+impl Display for PerfCounter {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let name = match self {
+            PerfCounter::Instructions => "Instructions",
+            PerfCounter::Cycles => "Cycles",
+            PerfCounter::CacheReferences => "Cache references",
+            PerfCounter::CacheMisses => "Cache misses",
+            PerfCounter::BranchInstructions => "Branch instructions",
+            PerfCounter::BranchMisses => "Branch misses",
+        };
+        f.write_str(name)
+    }
+}
+
+/// The mean count of each available counter per iteration of a benchmark.
+#[derive(Clone, Debug)]
+pub struct PerfStats {
+    pub(crate) counts: Vec<(PerfCounter, f64)>,
+}
+
+impl PerfStats {
+    pub(crate) fn new(counts: Vec<(PerfCounter, u64)>, iters: usize) -> Self {
+        let counts = counts
+            .into_iter()
+            .map(|(counter, count)| (counter, count as f64 / iters as f64))
+            .collect();
+        Self { counts }
+    }
+
+    /// The mean count per iteration, if the counter was available.
+    pub fn get(&self, counter: PerfCounter) -> Option<f64> {
+        self.counts
+            .iter()
+            .find(|(c, _)| *c == counter)
+            .map(|(_, count)| *count)
+    }
+
+    /// Iterates over the available counters and their mean count per iteration.
+    pub fn iter(&self) -> impl Iterator<Item = (PerfCounter, f64)> {
+        self.counts.iter().copied()
+    }
+}
+
+/// Whether a warning about unavailable counters was already printed. Only the first one is
+/// printed, as the reason is usually the same for all benchmarks.
+static WARNED: AtomicBool = AtomicBool::new(false);
+
+fn warn_unavailable(reason: impl Display) {
+    if !WARNED.swap(true, Relaxed) {
+        eprintln!("warning: perf counters are unavailable: {reason}");
+    }
+}
+
+/// Scales a count which was only measured while the counter was running, because the kernel
+/// had to multiplex the counters, to the whole time it was enabled.
+fn scale_count(count: u64, time_enabled: u64, time_running: u64) -> u64 {
+    if time_running == 0 {
+        return 0;
+    }
+    (count as f64 * time_enabled as f64 / time_running as f64) as u64
+}
+
+#[cfg(target_os = "linux")]
+pub(crate) use linux::{close, disable, enable, open, take_counts};
+
+#[cfg(not(target_os = "linux"))]
+pub(crate) use unsupported::{close, disable, enable, open, take_counts};
+
+#[cfg(target_os = "linux")]
+mod linux {
+    use std::{
+        cell::RefCell,
+        fs::File,
+        io,
+        os::fd::{AsRawFd, FromRawFd},
+        ptr,
+    };
+
+    use super::{PerfCounter, scale_count, warn_unavailable};
+
+    const PERF_TYPE_HARDWARE: u32 = 0;
+    const PERF_FORMAT_TOTAL_TIME_ENABLED: u64 = 1 << 0;
+    const PERF_FORMAT_TOTAL_TIME_RUNNING: u64 = 1 << 1;
+    const PERF_FORMAT_GROUP: u64 = 1 << 3;
+    const PERF_FLAG_FD_CLOEXEC: libc::c_ulong = 1 << 3;
+    const PERF_IOC_FLAG_GROUP: libc::c_ulong = 1;
+
+    // bits of the `perf_event_attr` flags bitfield
+    const ATTR_DISABLED: u64 = 1 << 0;
+    const ATTR_EXCLUDE_KERNEL: u64 = 1 << 5;
+    const ATTR_EXCLUDE_HV: u64 = 1 << 6;
+
+    // `_IO('$', nr)`, where the direction bits of `_IOC_NONE` differ between architectures
+    #[cfg(any(
+        target_arch = "powerpc",
+        target_arch = "powerpc64",
+        target_arch = "mips",
+        target_arch = "mips64",
+        target_arch = "sparc",
+        target_arch = "sparc64"
+    ))]
+    const IOC_NONE: libc::c_ulong = 1 << 29;
+    #[cfg(not(any(
+        target_arch = "powerpc",
+        target_arch = "powerpc64",
+        target_arch = "mips",
+        target_arch = "mips64",
+        target_arch = "sparc",
+        target_arch = "sparc64"
+    )))]
+    const IOC_NONE: libc::c_ulong = 0;
+    const PERF_EVENT_IOC_ENABLE: libc::c_ulong = IOC_NONE | (b'$' as libc::c_ulong) << 8;
+    const PERF_EVENT_IOC_DISABLE: libc::c_ulong = IOC_NONE | (b'$' as libc::c_ulong) << 8 | 1;
+    const PERF_EVENT_IOC_RESET: libc::c_ulong = IOC_NONE | (b'$' as libc::c_ulong) << 8 | 3;
+
+    /// The first version of `struct perf_event_attr` (`PERF_ATTR_SIZE_VER0`), which contains
+    /// all fields we need. The kernel treats the missing fields as zero.
+    #[repr(C)]
+    #[derive(Default)]
+    struct PerfEventAttr {
+        type_: u32,
+        size: u32,
+        config: u64,
+        sample_period: u64,
+        sample_type: u64,
+        read_format: u64,
+        flags: u64,
+        wakeup_events: u32,
+        bp_type: u32,
+        config1: u64,
+    }
+
+    /// Counters which are scheduled together, so their counts refer to the same period.
+    struct CounterGroup {
+        /// The group leader is the first file.
+        files: Vec<File>,
+        counters: Vec<PerfCounter>,
+        /// The times when the counts were last taken. Unlike the counts, the times are not
+        /// reset, so the counts are scaled with the difference to these.
+        time_enabled: u64,
+        time_running: u64,
+    }
+
+    thread_local! {
+        static GROUP: RefCell<Option<CounterGroup>> = const { RefCell::new(None) };
+    }
+
+    /// Opens the counters for the current thread. Counters which are unavailable are skipped
+    /// with a warning.
+    pub(crate) fn open(counters: &[PerfCounter]) {
+        let mut group = CounterGroup {
+            files: Vec::with_capacity(counters.len()),
+            counters: Vec::with_capacity(counters.len()),
+            time_enabled: 0,
+            time_running: 0,
+        };
+        for &counter in counters {
+            let leader = group.files.first().map_or(-1, |file| file.as_raw_fd());
+            match open_counter(counter, leader) {
+                Ok(file) => {
+                    group.files.push(file);
+                    group.counters.push(counter);
+                }
+                Err(err) => warn_unavailable(format_args!("{counter}: {err}")),
+            }
+        }
+        if !group.files.is_empty() {
+            GROUP.set(Some(group));
+        }
+    }
+
+    fn open_counter(counter: PerfCounter, leader: libc::c_int) -> io::Result<File> {
+        let config = match counter {
+            PerfCounter::Cycles => 0,
+            PerfCounter::Instructions => 1,
+            PerfCounter::CacheReferences => 2,
+            PerfCounter::CacheMisses => 3,
+            PerfCounter::BranchInstructions => 4,
+            PerfCounter::BranchMisses => 5,
+        };
+        let mut flags = ATTR_EXCLUDE_KERNEL | ATTR_EXCLUDE_HV;
+        if leader == -1 {
+            // the members of the group are enabled and disabled with the leader
+            flags |= ATTR_DISABLED;
+        }
+        let attr = PerfEventAttr {
+            type_: PERF_TYPE_HARDWARE,
+            size: size_of::<PerfEventAttr>() as u32,
+            config,
+            read_format: PERF_FORMAT_GROUP
+                | PERF_FORMAT_TOTAL_TIME_ENABLED
+                | PERF_FORMAT_TOTAL_TIME_RUNNING,
+            flags,
+            ..Default::default()
+        };
+        // Safety: attr is a valid `perf_event_attr` with the size it claims
+        let fd = unsafe {
+            libc::syscall(
+                libc::SYS_perf_event_open,
+                ptr::from_ref(&attr),
+                0,
+                -1,
+                leader,
+                PERF_FLAG_FD_CLOEXEC,
+            )
+        };
+        if fd < 0 {
+            return Err(io::Error::last_os_error());
+        }
+        // Safety: the syscall returned a new file descriptor which we own
+        Ok(unsafe { File::from_raw_fd(fd as libc::c_int) })
+    }
+
+    fn group_ioctl(request: libc::c_ulong) {
+        GROUP.with_borrow(|group| {
+            if let Some(group) = group {
+                // Safety: the leader is an open perf event file descriptor. Errors are ignored,
+                // they result in missing counts.
+                unsafe { libc::ioctl(group.files[0].as_raw_fd(), request, PERF_IOC_FLAG_GROUP) };
+            }
+        });
+    }
+
+    /// Starts counting, if counters are open.
+    #[inline(always)]
+    pub(crate) fn enable() {
+        group_ioctl(PERF_EVENT_IOC_ENABLE);
+    }
+
+    /// Stops counting, if counters are open.
+    #[inline(always)]
+    pub(crate) fn disable() {
+        group_ioctl(PERF_EVENT_IOC_DISABLE);
+    }
+
+    /// Returns the counts since the counters were opened or last taken and resets them. The
+    /// counts are scaled if the kernel had to multiplex the counters.
+    pub(crate) fn take_counts() -> Option<Vec<(PerfCounter, u64)>> {
+        let counts = GROUP.with_borrow_mut(|group| {
+            let group = group.as_mut()?;
+            // nr, time_enabled, time_running, followed by one value per counter
+            let mut buf = vec![0_u64; 3 + group.files.len()];
+            let len = size_of_val(buf.as_slice());
+            // Safety: buf is valid for writes of `len` bytes
+            let read =
+                unsafe { libc::read(group.files[0].as_raw_fd(), buf.as_mut_ptr().cast(), len) };
+            if read != len as isize {
+                warn_unavailable(io::Error::last_os_error());
+                return None;
+            }
+            let enabled = buf[1].saturating_sub(group.time_enabled);
+            let running = buf[2].saturating_sub(group.time_running);
+            (group.time_enabled, group.time_running) = (buf[1], buf[2]);
+            let counts = group
+                .counters
+                .iter()
+                .zip(&buf[3..])
+                .map(|(&counter, &count)| (counter, scale_count(count, enabled, running)))
+                .collect();
+            Some(counts)
+        });
+        group_ioctl(PERF_EVENT_IOC_RESET);
+        counts
+    }
+
+    /// Closes the counters of the current thread.
+    pub(crate) fn close() {
+        GROUP.set(None);
+    }
+}
+
+#[cfg(not(target_os = "linux"))]
+mod unsupported {
+    use super::{PerfCounter, warn_unavailable};
+
+    pub(crate) fn open(_counters: &[PerfCounter]) {
+        warn_unavailable("only supported on Linux");
+    }
+
+    #[inline(always)]
+    pub(crate) fn enable() {}
+
+    #[inline(always)]
+    pub(crate) fn disable() {}
+
+    pub(crate) fn take_counts() -> Option<Vec<(PerfCounter, u64)>> {
+        None
+    }
+
+    pub(crate) fn close() {}
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{PerfCounter, PerfStats, close, open, scale_count, take_counts};
+
+    #[test]
+    fn test_per_iteration() {
+        let counts = vec![
+            (PerfCounter::Instructions, 1000),
+            (PerfCounter::Cycles, 250),
+        ];
+        let stats = PerfStats::new(counts, 100);
+        assert_eq!(stats.get(PerfCounter::Instructions), Some(10.0));
+        assert_eq!(stats.get(PerfCounter::Cycles), Some(2.5));
+        assert_eq!(stats.get(PerfCounter::CacheMisses), None);
+        let counters: Vec<_> = stats.iter().map(|(counter, _)| counter).collect();
+        assert_eq!(counters, [PerfCounter::Instructions, PerfCounter::Cycles]);
+    }
+
+    #[test]
+    fn test_scale_count() {
+        assert_eq!(scale_count(100, 10, 10), 100);
+        // counted for a quarter of the time
+        assert_eq!(scale_count(100, 40, 10), 400);
+        assert_eq!(scale_count(100, 10, 0), 0);
+    }
+
+    #[test]
+    fn test_no_counters_opened() {
+        // like when all counters fail to open
+        open(&[]);
+        assert!(take_counts().is_none());
+        close();
+    }
+
+    #[test]
+    #[cfg_attr(miri, ignore)]
+    fn test_open_default_counters() {
+        // the counters are unavailable in many environments, which must not panic
+        open(&PerfCounter::DEFAULT);
+        if let Some(counts) = take_counts() {
+            assert!(
+                counts
+                    .iter()
+                    .all(|(counter, _)| PerfCounter::DEFAULT.contains(counter))
+            );
+        }
+        close();
+        assert!(take_counts().is_none());
+    }
+}
@@ -0,0 +1,277 @@
+use crate::{Haste, label::Label, stats::Rng};
+
+/// A benchmark function registered with the `#[bench]` macro.
+#[doc(hidden)]
+pub struct Benchmark {
+    /// The name of the benchmarked function.
+    pub name: &'static str,
+    /// The `module_path!()` of the module generated by the macro.
+    pub module_path: &'static str,
+    pub group: Option<&'static str>,
+    /// Whether the benchmark is only run with `--ignored` or `--include-ignored`.
+    pub ignored: bool,
+    /// The index of the instantiation of a generic benchmark function, `0` otherwise.
+    pub instance: usize,
+    pub func: fn(Haste),
+}
+
+/// Serializes the tests which call [`Runner::run`](crate::Runner::run), as each run calls all
+/// registered benchmark functions of the test binary to collect their labels.
+#[cfg(test)]
+pub(crate) fn lock_runs() -> std::sync::MutexGuard<'static, ()> {
+    static LOCK: std::sync::Mutex<()> = std::sync::Mutex::new(());
+    LOCK.lock()
+        .unwrap_or_else(std::sync::PoisonError::into_inner)
+}
+
+impl Benchmark {
+    /// The label root of the benchmarks run by this function, without the parts of the
+    /// instantiation and args.
+    fn label(&self, strip_crate_prefix: bool) -> Label {
+        let mut label = Label::new(self.name).with_module_path(bench_module_path(self.module_path));
+        if let Some(group) = self.group {
+            label = label.with_group(group);
+        }
+        label.set_strip_crate_prefix(strip_crate_prefix);
+        label
+    }
+}
+
+/// Returns the module path of the benchmarked function from the `module_path!()` of the module
+/// generated by the `#[bench]` macro.
+#[doc(hidden)]
+pub fn bench_module_path(module_path: &str) -> &str {
+    module_path.trim_end_matches("::__haste_bench")
+}
+
+/// Whether the benchmark with this label is run. The `#[bench]` macro checks this before it
+/// creates the runtime and the args which are not needed for the label, so that collecting the
+/// labels doesn't run them.
+#[doc(hidden)]
+pub fn should_run(haste: &mut Haste, label: &Label) -> bool {
+    let label = haste.prepare_label(label.clone());
+    haste.preselect(&label)
+}
+
+/// A benchmark selected by the filter, identified by its function and the index among the
+/// benchmarks selected in a call of the function.
+pub(crate) struct Entry<'a> {
+    pub(crate) label: Label,
+    pub(crate) bench: &'a Benchmark,
+    pub(crate) index: usize,
+}
+
+/// Sorts the entries in the order they are run. They are sorted by label, which makes the order
+/// independent of the order in which the functions are registered by the linker. If a seed is
+/// provided, the sorted entries are shuffled with it.
+pub(crate) fn order(
+    entries: &mut [Entry<'_>],
+    strip_crate_prefix: bool,
+    shuffle_seed: Option<u64>,
+) {
+    // the function and index only matter for duplicate labels
+    entries.sort_by_cached_key(|entry| {
+        (
+            entry.label.to_string(),
+            entry.bench.label(strip_crate_prefix).to_string(),
+            entry.bench.instance,
+            entry.index,
+        )
+    });
+    if let Some(seed) = shuffle_seed {
+        // Fisher-Yates shuffle
+        let mut rng = Rng::new(seed);
+        for idx in (1..entries.len()).rev() {
+            entries.swap(idx, rng.below(idx + 1));
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::atomic::{AtomicUsize, Ordering::Relaxed};
+
+    use crate::{Haste, Label, Runner};
+
+    use super::{Benchmark, Entry, lock_runs, order};
+
+    fn bench(name: &'static str, instance: usize) -> Benchmark {
+        fn noop(_: Haste) {}
+        Benchmark {
+            name,
+            module_path: "my_crate::__haste_bench",
+            group: None,
+            ignored: false,
+            instance,
+            func: noop,
+        }
+    }
+
+    #[test]
+    fn test_order() {
+        let (fill, fill_1, direct) = (bench("fill", 0), bench("fill", 1), bench("direct", 0));
+        // a function taking `Haste` chooses labels which differ from its name
+        let mut entries = vec![
+            Entry {
+                label: Label::new("fill").with_part(1),
+                bench: &fill_1,
+                index: 0,
+            },
+            Entry {
+                label: Label::new("fill").with_part(2),
+                bench: &fill,
+                index: 1,
+            },
+            Entry {
+                label: Label::new("fill").with_part(1),
+                bench: &fill,
+                index: 0,
+            },
+            Entry {
+                label: Label::new("add"),
+                bench: &direct,
+                index: 0,
+            },
+        ];
+        let keys = |entries: &[Entry]| {
+            entries
+                .iter()
+                .map(|entry| (entry.label.to_string(), entry.bench.instance, entry.index))
+                .collect::<Vec<_>>()
+        };
+        order(&mut entries, true, None);
+        let sorted = keys(&entries);
+        assert_eq!(
+            sorted,
+            [
+                ("add".to_string(), 0, 0),
+                ("fill/1".to_string(), 0, 0),
+                ("fill/1".to_string(), 1, 0),
+                ("fill/2".to_string(), 0, 1)
+            ]
+        );
+        order(&mut entries, true, Some(42));
+        let shuffled = keys(&entries);
+        order(&mut entries, true, None);
+        order(&mut entries, true, Some(42));
+        assert_eq!(shuffled, keys(&entries));
+    }
+
+    static SETUP_RUNS: AtomicUsize = AtomicUsize::new(0);
+
+    fn counted<T>(value: T) -> T {
+        SETUP_RUNS.fetch_add(1, Relaxed);
+        value
+    }
+
+    #[crate::bench(args = [1, 2], setup = counted)]
+    fn listed_with_setup(arg: &i32) -> i32 {
+        *arg
+    }
+
+    #[crate::bench(args = [counted(1), counted(2)], arg_names = ["one", "two"])]
+    fn listed_with_names(arg: i32) -> i32 {
+        arg
+    }
+
+    // linkme's distributed slices are not supported by miri
+    #[test]
+    #[cfg_attr(miri, ignore)]
+    fn test_list_does_not_run_setup() {
+        let _lock = lock_runs();
+        let results = Runner::new().args(["bench", "--list", "listed_"]).run();
+        let labels: Vec<_> = results.listed.iter().map(|l| l.to_string()).collect();
+        assert_eq!(
+            labels,
+            [
+                "registry::tests::listed_with_names/one",
+                "registry::tests::listed_with_names/two",
+                "registry::tests::listed_with_setup/1",
+                "registry::tests::listed_with_setup/2",
+            ]
+        );
+        assert_eq!(SETUP_RUNS.load(Relaxed), 0);
+    }
+
+    static RUN_SETUP_RUNS: AtomicUsize = AtomicUsize::new(0);
+    static HASTE_FN_RUNS: AtomicUsize = AtomicUsize::new(0);
+
+    fn run_counted<T>(value: T) -> T {
+        RUN_SETUP_RUNS.fetch_add(1, Relaxed);
+        value
+    }
+
+    #[crate::bench(consts = [1, 2], args = [1, 2], setup = run_counted)]
+    fn run_with_setup<const N: usize>(arg: &i32) -> i32 {
+        *arg * N as i32
+    }
+
+    #[crate::bench]
+    fn run_with_haste(mut haste: Haste) {
+        // code before the `bench*` calls is run again for each benchmark
+        HASTE_FN_RUNS.fetch_add(1, Relaxed);
+        haste.bench("run_with_haste/1", || 1);
+        haste.bench("run_with_haste/2", || 2);
+    }
+
+    #[test]
+    #[cfg_attr(miri, ignore)]
+    fn test_setup_runs_once_per_benchmark() {
+        let _lock = lock_runs();
+        // other runs also call the function taking `Haste` to collect its labels
+        HASTE_FN_RUNS.store(0, Relaxed);
+        let results = Runner::new()
+            .args([
+                "bench",
+                "run_with_",
+                "--warmup-time",
+                "1ms",
+                "--measurement-time",
+                "1ms",
+                "--sample-count",
+                "2",
+            ])
+            .run();
+        assert_eq!(results.len(), 6);
+        // once per instance and arg, not during the collection of the labels
+        assert_eq!(RUN_SETUP_RUNS.load(Relaxed), 4);
+        // once to collect the labels and once per benchmark
+        assert_eq!(HASTE_FN_RUNS.load(Relaxed), 3);
+    }
+
+    #[cfg(feature = "tokio")]
+    static RUNTIMES: AtomicUsize = AtomicUsize::new(0);
+
+    #[cfg(feature = "tokio")]
+    fn counted_runtime() -> tokio::runtime::Runtime {
+        RUNTIMES.fetch_add(1, Relaxed);
+        tokio::runtime::Runtime::new().unwrap()
+    }
+
+    #[cfg(feature = "tokio")]
+    #[crate::bench(args = [1, 2], runtime = counted_runtime())]
+    async fn async_with_runtime(arg: i32) -> i32 {
+        arg
+    }
+
+    #[test]
+    #[cfg(feature = "tokio")]
+    #[cfg_attr(miri, ignore)]
+    fn test_runtime_created_once_per_benchmark() {
+        let _lock = lock_runs();
+        let results = Runner::new()
+            .args([
+                "bench",
+                "async_with_runtime",
+                "--warmup-time",
+                "1ms",
+                "--measurement-time",
+                "1ms",
+                "--sample-count",
+                "2",
+            ])
+            .run();
+        assert_eq!(results.len(), 2);
+        assert_eq!(RUNTIMES.load(Relaxed), 2);
+    }
+}
@@ -0,0 +1,394 @@
+use std::{
+    ffi::OsString,
+    process,
+    sync::Arc,
+    time::{Duration, SystemTime, UNIX_EPOCH},
+};
+
+use clap::Parser;
+
+#[cfg(feature = "perf")]
+use crate::perf::PerfCounter;
+use crate::{
+    __private, Haste,
+    baseline::{self, Baseline},
+    bench_result::Results,
+    bencher::Selection,
+    cli::{self, Cli, ListFormat, TimerKind},
+    config::{Config, Overrides},
+    output,
+    registry::{self, Entry},
+    sampling_mode::SamplingMode,
+    timer::{self, Timer},
+};
+
+/// A configurable entry point that runs all registered benchmarks.
+///
+/// Values set on the runner are the defaults for all benchmarks. Benchmarks can still override
+/// them, e.g. with [`Haste::with_warmup`]. When command line arguments are parsed with
+/// [`Runner::parse_args`], options provided on the command line (e.g. `--warmup-time`) take
+/// precedence over both.
+///
+/// ```no_run
+/// use std::time::Duration;
+///
+/// fn main() {
+///     let results = haste::Runner::new()
+///         .warmup(Duration::from_millis(100))
+///         .sample_time(Duration::from_secs(1))
+///         .parse_args()
+///         .run();
+///     assert!(!results.is_empty());
+/// }
+/// ```
+#[derive(Debug, Default)]
+pub struct Runner {
+    config: Config,
+    shuffle_seed: Option<u64>,
+    cli: Option<Cli>,
+}
+
+impl Runner {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn warmup(mut self, duration: Duration) -> Self {
+        self.config.warmup = duration;
+        self
+    }
+
+    pub fn sample_time(mut self, duration: Duration) -> Self {
+        self.config.sample_time = duration;
+        self
+    }
+
+    /// Sets the number of samples to collect. Must be positive.
+    pub fn sample_count(mut self, sample_count: usize) -> Self {
+        assert!(sample_count > 0, "sample count must be positive");
+        self.config.sample_count = sample_count;
+        self
+    }
+
+    pub fn sampling_mode(mut self, sampling_mode: SamplingMode) -> Self {
+        self.config.sampling_mode = sampling_mode;
+        self
+    }
+
+    /// Sets the timer which measures the benchmarks. See [`timer`](crate::timer).
+    pub fn timer(mut self, timer: impl Timer + 'static) -> Self {
+        self.config.timer = Arc::new(timer);
+        self
+    }
+
+    /// Whether the calibrated overhead of reading the timer is subtracted from each
+    /// measurement. Disabled by default.
+    pub fn subtract_timer_overhead(mut self, subtract: bool) -> Self {
+        self.config.subtract_timer_overhead = subtract;
+        self
+    }
+
+    /// Whether the overhead of the benchmark loop is subtracted from the iteration times. The
+    /// overhead is measured by benchmarking an empty function with the same return type and
+    /// sample sizes. Disabled by default.
+    pub fn subtract_loop_overhead(mut self, subtract: bool) -> Self {
+        self.config.subtract_loop_overhead = subtract;
+        self
+    }
+
+    /// Whether the crate name is stripped from the module path in benchmark labels, e.g.
+    /// `json::parse` instead of `my_crate::json::parse`. Enabled by default.
+    pub fn strip_crate_prefix(mut self, strip: bool) -> Self {
+        self.config.strip_crate_prefix = strip;
+        self
+    }
+
+    /// Sets the hardware counters which are read for each benchmark. An empty slice disables
+    /// them. By default, instructions, cycles, cache misses and branch misses are counted.
+    #[cfg(feature = "perf")]
+    pub fn perf_counters(mut self, counters: &[PerfCounter]) -> Self {
+        self.config.perf_counters = counters.to_vec();
+        self
+    }
+
+    /// Runs the benchmarks in a random order determined by `seed`, which can help to detect
+    /// effects of the order on the results. By default, benchmarks are run sorted by label.
+    pub fn shuffle(mut self, seed: u64) -> Self {
+        self.shuffle_seed = Some(seed);
+        self
+    }
+
+    /// Only run benchmarks whose label matches the provided regex.
+    ///
+    /// # Panics
+    /// If `regex` is not a valid regex.
+    pub fn filter(mut self, regex: &str) -> Self {
+        self.config
+            .filter
+            .set_pattern(regex.to_string())
+            .unwrap_or_else(|err| panic!("invalid filter regex: {err}"));
+        self
+    }
+
+    /// Parses the command line arguments of the current process. See
+    /// `cargo bench -- --help` for the available options.
+    pub fn parse_args(self) -> Self {
+        self.args(std::env::args_os())
+    }
+
+    /// Parses the provided arguments as command line arguments. The first argument is the
+    /// binary name.
+    ///
+    /// Prints an error and exits the process if the arguments are invalid.
+    pub fn args<I, T>(mut self, args: I) -> Self
+    where
+        I: IntoIterator<Item = T>,
+        T: Into<OsString> + Clone,
+    {
+        self.cli = Some(Cli::parse_from(args));
+        self
+    }
+
+    /// Runs all registered benchmarks and returns their results.
+    pub fn run(self) -> Results {
+        let Runner {
+            mut config,
+            mut shuffle_seed,
+            cli,
+        } = self;
+        let cli = cli.unwrap_or_default();
+        config.filter.exact = cli.exact;
+        config.filter.skip = cli.skip;
+        // the pattern is set again, as it is only a regex without `--exact`
+        let pattern = cli.filter.or(config.filter.pattern().map(str::to_string));
+        if let Some(pattern) = pattern
+            && let Err(err) = config.filter.set_pattern(pattern)
+        {
+            eprintln!("error: invalid filter regex: {err}");
+            process::exit(1);
+        }
+        if cli.shuffle_seed.is_some() {
+            shuffle_seed = cli.shuffle_seed;
+        } else if cli.shuffle {
+            let seed = SystemTime::now()
+                .duration_since(UNIX_EPOCH)
+                .map_or(0, |time| time.as_nanos() as u64);
+            eprintln!("Shuffling benchmarks with seed {seed}");
+            shuffle_seed = Some(seed);
+        }
+        config.overrides = Overrides {
+            warmup: cli.warmup_time,
+            sample_time: cli.measurement_time,
+            sample_count: cli.sample_count,
+            timer: cli.timer.map(|kind| match new_timer(kind) {
+                Some(timer) => timer,
+                None => {
+                    eprintln!("error: the selected timer is not supported on this system");
+                    process::exit(1);
+                }
+            }),
+            subtract_timer_overhead: cli.subtract_timer_overhead.then_some(true),
+            subtract_loop_overhead: cli.subtract_loop_overhead.then_some(true),
+        };
+        config.baseline =
+            cli.baseline.map(
+                |name| match Baseline::load(&baseline::target_dir(), &name) {
+                    Ok(baseline) => Arc::new(baseline),
+                    Err(err) => {
+                        eprintln!("error: failed to load baseline `{name}`: {err}");
+                        process::exit(1);
+                    }
+                },
+            );
+
+        let benchmarks = __private::BENCHMARKS.iter().filter(|bench| {
+            if cli.ignored {
+                bench.ignored
+            } else {
+                cli.include_ignored || !bench.ignored
+            }
+        });
+        // The labels are only known after the functions were called, so they are first called to
+        // collect the labels of the selected benchmarks without running them.
+        let mut results = Results::default();
+        let mut entries = Vec::new();
+        for bench in benchmarks {
+            let mut haste = Haste::new(&mut results);
+            haste.set_config(config.clone());
+            haste.set_selection(Selection::Collect);
+            (bench.func)(haste);
+            entries.extend(
+                results
+                    .listed
+                    .drain(..)
+                    .enumerate()
+                    .map(|(index, label)| Entry {
+                        label,
+                        bench,
+                        index,
+                    }),
+            );
+        }
+        registry::order(&mut entries, config.strip_crate_prefix, shuffle_seed);
+
+        // nothing is run, so there are no results to write or save
+        if cli.list {
+            for entry in &entries {
+                println!("{}: benchmark", entry.label);
+            }
+            if let ListFormat::Pretty = cli.format {
+                println!();
+                println!("{}", cli::list_summary(entries.len()));
+            }
+            results.listed = entries.into_iter().map(|entry| entry.label).collect();
+            return results;
+        }
+
+        for entry in &entries {
+            let mut haste = Haste::new(&mut results);
+            haste.set_config(config.clone());
+            haste.set_selection(Selection::Only(entry.index));
+            (entry.bench.func)(haste);
+        }
+
+        if let Some(format) = cli.output_format
+            && let Err(err) = output::write_results(&results, format, cli.output.as_deref())
+        {
+            eprintln!("error: failed to write results: {err}");
+            process::exit(1);
+        }
+
+        if let Some(name) = cli.save_baseline {
+            // merge into an existing baseline so that filtered runs only update their benchmarks
+            let target_dir = baseline::target_dir();
+            let mut baseline = Baseline::load(&target_dir, &name).unwrap_or_default();
+            baseline.extend(&results);
+            if let Err(err) = baseline.save(&target_dir, &name) {
+                eprintln!("error: failed to save baseline `{name}`: {err}");
+                process::exit(1);
+            }
+        }
+
+        results
+    }
+}
+
+/// Creates the built-in timer, if it is supported on this system.
+fn new_timer(kind: TimerKind) -> Option<Arc<dyn Timer>> {
+    match kind {
+        TimerKind::Wall => Some(Arc::new(timer::WallTime::new())),
+        #[cfg(target_arch = "x86_64")]
+        TimerKind::Tsc => timer::Tsc::new().map(|tsc| Arc::new(tsc) as Arc<dyn Timer>),
+        #[cfg(unix)]
+        TimerKind::ThreadCpu => Some(Arc::new(timer::ThreadCpuTime)),
+        #[cfg(unix)]
+        TimerKind::ProcessCpu => Some(Arc::new(timer::ProcessCpuTime)),
+        #[allow(unreachable_patterns)]
+        _ => None,
+    }
+}
+
+// the CPU timers are only available on unix
+#[cfg(all(test, unix))]
+mod tests {
+    use std::{any::TypeId, time::Duration};
+
+    use crate::{
+        BenchResult, Haste, Results, Runner,
+        registry::lock_runs,
+        timer::{ProcessCpuTime, ThreadCpuTime, WallTime},
+    };
+
+    #[crate::bench]
+    fn precedence_runner() -> u8 {
+        1
+    }
+
+    #[crate::bench(warmup = Duration::from_millis(2), sample_count = 3, timer = WallTime::new())]
+    fn precedence_bench() -> u8 {
+        1
+    }
+
+    #[crate::bench]
+    fn precedence_haste(mut haste: Haste) {
+        haste
+            .with_timer_overhead_subtracted(false)
+            .with_loop_overhead_subtracted(false)
+            .bench("precedence_haste", || 1);
+    }
+
+    fn run(cli_args: &[&str]) -> Results {
+        let _lock = lock_runs();
+        let args = ["bench", "precedence_", "--measurement-time", "1ms"];
+        Runner::new()
+            .warmup(Duration::from_millis(1))
+            .sample_count(5)
+            .timer(ThreadCpuTime)
+            .subtract_timer_overhead(true)
+            .subtract_loop_overhead(true)
+            .args(args.iter().chain(cli_args))
+            .run()
+    }
+
+    fn get<'a>(results: &'a Results, label: &str) -> &'a BenchResult {
+        results
+            .get(label)
+            .unwrap_or_else(|| panic!("no result for {label}"))
+    }
+
+    fn timer_type(res: &BenchResult) -> TypeId {
+        res.config.timer.as_ref().type_id()
+    }
+
+    #[test]
+    #[cfg_attr(miri, ignore)]
+    fn test_benchmark_overrides_runner() {
+        let results = run(&[]);
+        assert_eq!(results.len(), 3);
+
+        let runner = get(&results, "runner::tests::precedence_runner");
+        assert_eq!(runner.config.warmup, Duration::from_millis(1));
+        assert_eq!(runner.config.sample_count, 5);
+        assert_eq!(timer_type(runner), TypeId::of::<ThreadCpuTime>());
+        assert!(runner.config.subtract_timer_overhead);
+        assert!(runner.config.subtract_loop_overhead);
+
+        let bench = get(&results, "runner::tests::precedence_bench");
+        assert_eq!(bench.config.warmup, Duration::from_millis(2));
+        assert_eq!(bench.config.sample_count, 3);
+        assert_eq!(timer_type(bench), TypeId::of::<WallTime>());
+
+        let haste = get(&results, "precedence_haste");
+        assert!(!haste.config.subtract_timer_overhead);
+        assert!(!haste.config.subtract_loop_overhead);
+    }
+
+    #[test]
+    #[cfg_attr(miri, ignore)]
+    fn test_cli_overrides_benchmark() {
+        let results = run(&[
+            "--warmup-time",
+            "3ms",
+            "--sample-count",
+            "2",
+            "--timer",
+            "process-cpu",
+            "--subtract-timer-overhead",
+            "--subtract-loop-overhead",
+        ]);
+        assert_eq!(results.len(), 3);
+        for res in results.iter() {
+            assert_eq!(res.config.warmup, Duration::from_millis(3), "{}", res.label);
+            assert_eq!(res.config.sample_count, 2, "{}", res.label);
+            assert_eq!(res.samples.len(), 2, "{}", res.label);
+            assert_eq!(
+                timer_type(res),
+                TypeId::of::<ProcessCpuTime>(),
+                "{}",
+                res.label
+            );
+            assert!(res.config.subtract_timer_overhead, "{}", res.label);
+            assert!(res.config.subtract_loop_overhead, "{}", res.label);
+        }
+    }
+}
@@ -1,17 +1,49 @@
-use std::time::Duration;
+use serde::{Deserialize, Serialize};
 
-pub(crate) struct Sample {
-    pub(crate) iter_time_ns: f64,
-    #[allow(dead_code)]
+/// A single measurement of running the benchmarked function `sample_size` times.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct Sample {
+    pub(crate) iter_time: f64,
     pub(crate) sample_size: usize,
 }
 
 impl Sample {
-    pub(crate) fn from_duration(duration: Duration, sample_size: usize) -> Self {
-        let iter_time_ns = (duration.as_nanos() as f64) / (sample_size as f64);
+    /// The mean time of one iteration in this sample, in the [`Unit`](crate::Unit) of the timer.
+    pub fn iter_time(&self) -> f64 {
+        self.iter_time
+    }
+
+    /// The number of iterations in this sample.
+    pub fn sample_size(&self) -> usize {
+        self.sample_size
+    }
+
+    /// Creates a sample from the measurement of `sample_size` iterations.
+    pub(crate) fn from_measurement(value: u64, sample_size: usize) -> Self {
+        let iter_time = (value as f64) / (sample_size as f64);
         Self {
-            iter_time_ns,
+            iter_time,
             sample_size,
         }
     }
+
+    /// Subtracts `overhead` from the iteration time, which is at least zero.
+    pub(crate) fn subtract_overhead(&mut self, overhead: f64) {
+        self.iter_time = (self.iter_time - overhead).max(0.0);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::Sample;
+
+    #[test]
+    fn test_subtract_overhead() {
+        let mut sample = Sample::from_measurement(100, 10);
+        sample.subtract_overhead(4.0);
+        assert_eq!(sample.iter_time(), 6.0);
+        sample.subtract_overhead(10.0);
+        assert_eq!(sample.iter_time(), 0.0);
+        assert_eq!(sample.sample_size(), 10);
+    }
 }
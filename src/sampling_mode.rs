@@ -2,13 +2,29 @@ use std::{cmp, time::Duration};
 
 use crate::config::Config;
 
-#[derive(Debug)]
-pub(crate) enum SamplingMode {
+/// How the number of iterations per sample is chosen.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum SamplingMode {
+    /// Use [`SamplingMode::Linear`] unless the benchmarked function is so slow that this would
+    /// exceed the sample time, in which case [`SamplingMode::Flat`] is used.
+    #[default]
+    Auto,
+    /// Increase the number of iterations linearly with each sample (`d, 2d, 3d, ...`). This
+    /// allows estimating the per-iteration time with a linear regression.
+    Linear,
+    /// Use the same number of iterations for each sample. Better suited for long-running
+    /// functions.
+    Flat,
+}
+
+/// The sampling mode chosen for a benchmark, see [`SamplingMode`].
+#[derive(Clone, Copy, Debug)]
+pub(crate) enum ActualSamplingMode {
     Linear { sampling_factor: usize },
     Flat { sample_size: usize },
 }
 
-impl SamplingMode {
+impl ActualSamplingMode {
     pub(crate) fn decide_mode(config: &Config, warmup_time: Duration, warmup_iters: usize) -> Self {
         let warmup_time = warmup_time.as_nanos() as f64;
         let warmup_mean = warmup_time / warmup_iters as f64;
@@ -24,21 +40,25 @@ impl SamplingMode {
             cmp::max(d.ceil() as usize, 1)
         };
 
-        if sampling_factor == 1 {
+        let flat = || {
             // target_time = c.sample_count * sample_size * mean
             let sample_size = target_time / warmup_mean / config.sample_count as f64;
             Self::Flat {
-                sample_size: sample_size.ceil() as usize,
+                sample_size: cmp::max(sample_size.ceil() as usize, 1),
             }
-        } else {
-            Self::Linear { sampling_factor }
+        };
+
+        match config.sampling_mode {
+            SamplingMode::Auto if sampling_factor == 1 => flat(),
+            SamplingMode::Auto | SamplingMode::Linear => Self::Linear { sampling_factor },
+            SamplingMode::Flat => flat(),
         }
     }
 
     pub(crate) fn sample_sizes(&self, config: &Config) -> impl Iterator<Item = usize> {
         (1..=config.sample_count).map(move |iter| match self {
-            SamplingMode::Linear { sampling_factor } => iter * sampling_factor,
-            SamplingMode::Flat { sample_size } => *sample_size,
+            ActualSamplingMode::Linear { sampling_factor } => iter * sampling_factor,
+            ActualSamplingMode::Flat { sample_size } => *sample_size,
         })
     }
 }
@@ -0,0 +1,346 @@
+use std::cmp::Ordering;
+
+/// A confidence interval around a point estimate.
+#[derive(Clone, Copy, Debug)]
+pub struct ConfidenceInterval {
+    pub(crate) lower: f64,
+    pub(crate) upper: f64,
+    pub(crate) confidence_level: f64,
+}
+
+/// Bootstrap estimates of the mean and median of a set of values.
+#[derive(Clone, Copy, Debug)]
+pub(crate) struct Bootstrap {
+    pub(crate) mean: ConfidenceInterval,
+    pub(crate) median: ConfidenceInterval,
+}
+
+impl Bootstrap {
+    /// Computes confidence intervals for the mean and median of `values` by resampling
+    /// `values` with replacement `resamples` times and taking the percentiles of the resulting
+    /// distribution of statistics.
+    pub(crate) fn new(values: &[f64], resamples: usize, confidence_level: f64) -> Self {
+        assert!(!values.is_empty(), "can't bootstrap an empty sample");
+        assert!(
+            resamples > 0,
+            "number of bootstrap resamples must be positive"
+        );
+
+        let mut rng = Rng::new(BOOTSTRAP_SEED);
+        let mut resample = vec![0.0; values.len()];
+        let mut means = Vec::with_capacity(resamples);
+        let mut medians = Vec::with_capacity(resamples);
+        for _ in 0..resamples {
+            for slot in resample.iter_mut() {
+                *slot = values[rng.below(values.len())];
+            }
+            means.push(mean(&resample));
+            sort(&mut resample);
+            medians.push(percentile_of_sorted(&resample, 50.0));
+        }
+
+        Self {
+            mean: ConfidenceInterval::from_distribution(&mut means, confidence_level),
+            median: ConfidenceInterval::from_distribution(&mut medians, confidence_level),
+        }
+    }
+}
+
+/// Bootstrap estimate of the relative change of the mean from `old` to `new`, e.g. `0.05` for a
+/// 5% increase.
+pub(crate) fn relative_mean_change(
+    old: &[f64],
+    new: &[f64],
+    resamples: usize,
+    confidence_level: f64,
+) -> (f64, ConfidenceInterval) {
+    assert!(
+        !old.is_empty() && !new.is_empty(),
+        "can't compare empty samples"
+    );
+    let mut rng = Rng::new(BOOTSTRAP_SEED);
+    let mut old_resample = vec![0.0; old.len()];
+    let mut new_resample = vec![0.0; new.len()];
+    let mut changes = Vec::with_capacity(resamples);
+    for _ in 0..resamples {
+        for slot in old_resample.iter_mut() {
+            *slot = old[rng.below(old.len())];
+        }
+        for slot in new_resample.iter_mut() {
+            *slot = new[rng.below(new.len())];
+        }
+        changes.push(mean(&new_resample) / mean(&old_resample) - 1.0);
+    }
+    let change = mean(new) / mean(old) - 1.0;
+    (
+        change,
+        ConfidenceInterval::from_distribution(&mut changes, confidence_level),
+    )
+}
+
+impl ConfidenceInterval {
+    pub fn lower(&self) -> f64 {
+        self.lower
+    }
+
+    pub fn upper(&self) -> f64 {
+        self.upper
+    }
+
+    /// The confidence level of the interval, e.g. `0.95`.
+    pub fn confidence_level(&self) -> f64 {
+        self.confidence_level
+    }
+
+    fn from_distribution(estimates: &mut [f64], confidence_level: f64) -> Self {
+        sort(estimates);
+        let alpha = 1.0 - confidence_level;
+        Self {
+            lower: percentile_of_sorted(estimates, 50.0 * alpha),
+            upper: percentile_of_sorted(estimates, 100.0 * (1.0 - alpha / 2.0)),
+            confidence_level,
+        }
+    }
+}
+
+/// An ordinary least-squares fit of `y = slope * x + intercept`.
+#[derive(Clone, Copy, Debug)]
+pub struct Regression {
+    pub(crate) slope: f64,
+    pub(crate) intercept: f64,
+    pub(crate) r_squared: f64,
+}
+
+impl Regression {
+    pub fn slope(&self) -> f64 {
+        self.slope
+    }
+
+    pub fn intercept(&self) -> f64 {
+        self.intercept
+    }
+
+    /// The coefficient of determination of the fit. Values close to `1.0` indicate a good fit.
+    /// Below [`Regression::POOR_FIT_R_SQUARED`], a warning is printed, as the slope and intercept
+    /// are then unreliable.
+    pub fn r_squared(&self) -> f64 {
+        self.r_squared
+    }
+
+    /// The R² below which a fit is considered poor, e.g. because the samples are dominated by
+    /// noise or the loop overhead.
+    pub const POOR_FIT_R_SQUARED: f64 = 0.9;
+
+    /// Whether the R² of the fit is below [`Regression::POOR_FIT_R_SQUARED`].
+    pub fn is_poor_fit(&self) -> bool {
+        self.r_squared < Self::POOR_FIT_R_SQUARED
+    }
+
+    /// Fits a line through the points `(xs[i], ys[i])`. Returns `None` if there are fewer than
+    /// two distinct `x` values.
+    pub(crate) fn fit(xs: &[f64], ys: &[f64]) -> Option<Self> {
+        assert_eq!(xs.len(), ys.len(), "xs and ys must have the same length");
+        if xs.len() < 2 {
+            return None;
+        }
+        let x_mean = mean(xs);
+        let y_mean = mean(ys);
+        let (mut sxx, mut sxy, mut syy) = (0.0, 0.0, 0.0);
+        for (x, y) in xs.iter().zip(ys) {
+            let (dx, dy) = (x - x_mean, y - y_mean);
+            sxx += dx * dx;
+            sxy += dx * dy;
+            syy += dy * dy;
+        }
+        if sxx == 0.0 {
+            return None;
+        }
+        let slope = sxy / sxx;
+        let intercept = y_mean - slope * x_mean;
+        // All points lie on a horizontal line, which is fit perfectly.
+        let r_squared = if syy == 0.0 {
+            1.0
+        } else {
+            (sxy * sxy) / (sxx * syy)
+        };
+        Some(Self {
+            slope,
+            intercept,
+            r_squared,
+        })
+    }
+}
+
+/// Classification of values as outliers using Tukey's fences.
+///
+/// Values outside of `[Q1 - 1.5 * IQR, Q3 + 1.5 * IQR]` are mild outliers and values outside
+/// of `[Q1 - 3 * IQR, Q3 + 3 * IQR]` are severe outliers, where `IQR = Q3 - Q1` is the
+/// interquartile range.
+#[derive(Clone, Copy, Debug, Default)]
+pub struct Outliers {
+    pub(crate) low_severe: usize,
+    pub(crate) low_mild: usize,
+    pub(crate) high_mild: usize,
+    pub(crate) high_severe: usize,
+    fences: [f64; 4],
+}
+
+impl Outliers {
+    pub(crate) fn classify(values: &[f64]) -> Self {
+        let mut sorted = values.to_vec();
+        sort(&mut sorted);
+        let q1 = percentile_of_sorted(&sorted, 25.0);
+        let q3 = percentile_of_sorted(&sorted, 75.0);
+        let iqr = q3 - q1;
+        let fences = [
+            q1 - 3.0 * iqr,
+            q1 - 1.5 * iqr,
+            q3 + 1.5 * iqr,
+            q3 + 3.0 * iqr,
+        ];
+        let mut outliers = Self {
+            fences,
+            ..Default::default()
+        };
+        for &value in values {
+            if value < fences[0] {
+                outliers.low_severe += 1;
+            } else if value < fences[1] {
+                outliers.low_mild += 1;
+            } else if value > fences[3] {
+                outliers.high_severe += 1;
+            } else if value > fences[2] {
+                outliers.high_mild += 1;
+            }
+        }
+        outliers
+    }
+
+    pub fn low_severe(&self) -> usize {
+        self.low_severe
+    }
+
+    pub fn low_mild(&self) -> usize {
+        self.low_mild
+    }
+
+    pub fn high_mild(&self) -> usize {
+        self.high_mild
+    }
+
+    pub fn high_severe(&self) -> usize {
+        self.high_severe
+    }
+
+    /// The total number of mild and severe outliers.
+    pub fn total(&self) -> usize {
+        self.low_severe + self.low_mild + self.high_mild + self.high_severe
+    }
+
+    pub(crate) fn is_severe(&self, value: f64) -> bool {
+        value < self.fences[0] || value > self.fences[3]
+    }
+}
+
+// Fixed seed so that repeated runs over identical samples produce identical intervals.
+const BOOTSTRAP_SEED: u64 = 0x6861_7374_655f_6273;
+
+pub(crate) fn mean(values: &[f64]) -> f64 {
+    values.iter().sum::<f64>() / values.len() as f64
+}
+
+pub(crate) fn sort(values: &mut [f64]) {
+    values.sort_unstable_by(|a, b| a.partial_cmp(b).unwrap_or(Ordering::Equal));
+}
+
+/// Returns the `pct` percentile of `sorted` using linear interpolation between the closest ranks.
+pub(crate) fn percentile_of_sorted(sorted: &[f64], pct: f64) -> f64 {
+    assert!(!sorted.is_empty());
+    assert!((0.0..=100.0).contains(&pct));
+    if sorted.len() == 1 {
+        return sorted[0];
+    }
+    let rank = pct / 100.0 * (sorted.len() - 1) as f64;
+    let lower = rank.floor() as usize;
+    let upper = rank.ceil() as usize;
+    let weight = rank - lower as f64;
+    sorted[lower] * (1.0 - weight) + sorted[upper] * weight
+}
+
+/// A small, non-cryptographic PRNG (SplitMix64) used for resampling.
+pub(crate) struct Rng {
+    state: u64,
+}
+
+impl Rng {
+    pub(crate) fn new(seed: u64) -> Self {
+        Self { state: seed }
+    }
+
+    pub(crate) fn next_u64(&mut self) -> u64 {
+        self.state = self.state.wrapping_add(0x9e37_79b9_7f4a_7c15);
+        let mut z = self.state;
+        z = (z ^ (z >> 30)).wrapping_mul(0xbf58_476d_1ce4_e5b9);
+        z = (z ^ (z >> 27)).wrapping_mul(0x94d0_49bb_1331_11eb);
+        z ^ (z >> 31)
+    }
+
+    /// Returns a value in `0..bound`.
+    pub(crate) fn below(&mut self, bound: usize) -> usize {
+        // Lemire's multiply-shift reduction. The slight bias is irrelevant for resampling.
+        ((self.next_u64() as u128 * bound as u128) >> 64) as usize
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{Bootstrap, Outliers, Regression, percentile_of_sorted};
+
+    #[test]
+    fn test_percentile() {
+        let sorted = [1.0, 2.0, 3.0, 4.0, 5.0];
+        assert_eq!(percentile_of_sorted(&sorted, 0.0), 1.0);
+        assert_eq!(percentile_of_sorted(&sorted, 50.0), 3.0);
+        assert_eq!(percentile_of_sorted(&sorted, 100.0), 5.0);
+        assert_eq!(percentile_of_sorted(&sorted, 12.5), 1.5);
+    }
+
+    #[test]
+    fn test_bootstrap_contains_estimate() {
+        let values: Vec<f64> = (0..100).map(|i| 100.0 + (i % 7) as f64).collect();
+        let bootstrap = Bootstrap::new(&values, 1000, 0.95);
+        let mean = values.iter().sum::<f64>() / values.len() as f64;
+        assert!(bootstrap.mean.lower <= mean && mean <= bootstrap.mean.upper);
+        assert!(bootstrap.median.lower <= bootstrap.median.upper);
+    }
+
+    #[test]
+    fn test_regression() {
+        let xs = [1.0, 2.0, 3.0, 4.0];
+        let ys = [12.0, 22.0, 32.0, 42.0];
+        let reg = Regression::fit(&xs, &ys).unwrap();
+        assert!((reg.slope - 10.0).abs() < 1e-9);
+        assert!((reg.intercept - 2.0).abs() < 1e-9);
+        assert!((reg.r_squared - 1.0).abs() < 1e-9);
+        assert!(!reg.is_poor_fit());
+
+        let reg = Regression::fit(&xs, &[10.0, 40.0, 10.0, 40.0]).unwrap();
+        assert!(reg.r_squared < 0.5);
+        assert!(reg.is_poor_fit());
+
+        assert!(Regression::fit(&[1.0, 1.0], &[1.0, 2.0]).is_none());
+    }
+
+    #[test]
+    fn test_outliers() {
+        let mut values: Vec<f64> = (0..20).map(|i| 10.0 + (i % 4) as f64).collect();
+        values.extend([17.5, 100.0, -100.0]);
+        let outliers = Outliers::classify(&values);
+        assert_eq!(outliers.low_severe, 1);
+        assert_eq!(outliers.low_mild, 0);
+        assert_eq!(outliers.high_mild, 1);
+        assert_eq!(outliers.high_severe, 1);
+        assert!(outliers.is_severe(100.0));
+        assert!(!outliers.is_severe(17.5));
+    }
+}
@@ -0,0 +1,321 @@
+//! Timers which measure the benchmarked functions.
+//!
+//! By default, benchmarks are measured with the [`WallTime`] timer. Use
+//! [`Haste::with_timer`](crate::Haste::with_timer), [`Runner::timer`](crate::Runner::timer) or
+//! the `--timer` command line option to choose another one.
+use std::{
+    any::Any,
+    fmt::{Debug, Display},
+    hint::black_box,
+    time::{Duration, Instant},
+};
+
+use serde::{Deserialize, Serialize};
+
+use crate::bench_result::{Scaled, scale_nanos};
+
+/// A source of measurements, e.g. a clock or a cycle counter.
+pub trait Timer: Any + Debug + Send + Sync {
+    /// Reads the current value of the timer. Only the difference between two readings is
+    /// meaningful.
+    fn read(&self) -> u64;
+
+    /// The unit of the difference between two readings.
+    fn unit(&self) -> Unit;
+}
+
+/// The unit of the measurements of a [`Timer`].
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Unit {
+    Nanoseconds,
+    /// Cycles of a cycle counter, e.g. the reference cycles of the TSC on x86_64.
+    Cycles,
+}
+
+impl Unit {
+    /// Formats `value` with a suitable prefix, e.g. `1.50 µs` or `2.10 Kcycles`.
+    pub(crate) fn scale(self, value: f64) -> Scaled {
+        match self {
+            Unit::Nanoseconds => scale_nanos(value),
+            Unit::Cycles => {
+                let magnitude = value.abs();
+                let (denom, unit) = if magnitude < 1000.0_f64.powi(1) {
+                    (1000.0_f64.powi(0), "cycles")
+                } else if magnitude < 1000.0_f64.powi(2) {
+                    (1000.0_f64.powi(1), "Kcycles")
+                } else if magnitude < 1000.0_f64.powi(3) {
+                    (1000.0_f64.powi(2), "Mcycles")
+                } else {
+                    (1000.0_f64.powi(3), "Gcycles")
+                };
+                Scaled {
+                    val: value / denom,
+                    unit,
+                }
+            }
+        }
+    }
+}
+
+impl Display for Unit {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Unit::Nanoseconds => f.write_str("ns"),
+            Unit::Cycles => f.write_str("cycles"),
+        }
+    }
+}
+
+/// The resolution and overhead of a timer, measured before its first use.
+#[derive(Clone, Copy, Debug)]
+pub(crate) struct Calibration {
+    pub(crate) unit: Unit,
+    /// The smallest observed non-zero difference between two readings.
+    pub(crate) resolution: u64,
+    /// The mean cost of one reading, which is included once in every measurement.
+    pub(crate) overhead: f64,
+}
+
+impl Calibration {
+    const RESOLUTION_ROUNDS: usize = 100;
+    /// Upper bound of readings per round, so that a timer which never changes is no problem.
+    const MAX_READS_PER_ROUND: usize = 1_000_000;
+    const OVERHEAD_ROUNDS: usize = 20;
+    const OVERHEAD_READS: u64 = 1000;
+
+    pub(crate) fn measure(timer: &dyn Timer) -> Self {
+        let mut resolution = u64::MAX;
+        for _ in 0..Self::RESOLUTION_ROUNDS {
+            let start = timer.read();
+            for _ in 0..Self::MAX_READS_PER_ROUND {
+                let end = timer.read();
+                if end != start {
+                    resolution = resolution.min(end.saturating_sub(start));
+                    break;
+                }
+            }
+        }
+
+        // the minimum over several rounds excludes rounds which were interrupted
+        let mut overhead = f64::INFINITY;
+        for _ in 0..Self::OVERHEAD_ROUNDS {
+            let start = timer.read();
+            for _ in 0..Self::OVERHEAD_READS {
+                black_box(timer.read());
+            }
+            let elapsed = timer.read().saturating_sub(start);
+            overhead = overhead.min(elapsed as f64 / (Self::OVERHEAD_READS + 1) as f64);
+        }
+        Self {
+            unit: timer.unit(),
+            resolution,
+            overhead,
+        }
+    }
+}
+
+impl Display for Calibration {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let Self {
+            unit,
+            resolution,
+            overhead,
+        } = *self;
+        if resolution == u64::MAX {
+            write!(f, "Resolution: unknown")?;
+        } else {
+            write!(f, "Resolution: {:.2}", unit.scale(resolution as f64))?;
+        }
+        write!(f, " | Overhead: {:.2}", unit.scale(overhead))
+    }
+}
+
+/// The elapsed wall-clock time measured with [`Instant`].
+#[derive(Debug)]
+pub struct WallTime {
+    origin: Instant,
+}
+
+impl WallTime {
+    pub fn new() -> Self {
+        Self {
+            origin: Instant::now(),
+        }
+    }
+}
+
+impl Default for WallTime {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Timer for WallTime {
+    #[inline(always)]
+    fn read(&self) -> u64 {
+        self.origin.elapsed().as_nanos() as u64
+    }
+
+    fn unit(&self) -> Unit {
+        Unit::Nanoseconds
+    }
+}
+
+/// The time stamp counter of x86_64 CPUs, read with `rdtsc`.
+///
+/// Measurements are reported in reference cycles of the TSC, which tick at a constant rate
+/// independent of the current CPU frequency. The rate is calibrated against [`Instant`].
+#[cfg(target_arch = "x86_64")]
+#[derive(Debug)]
+pub struct Tsc {
+    ticks_per_ns: f64,
+}
+
+#[cfg(target_arch = "x86_64")]
+impl Tsc {
+    /// How long the TSC is calibrated against [`Instant`].
+    const CALIBRATION_TIME: Duration = Duration::from_millis(10);
+
+    /// Calibrates the TSC. Returns `None` if the CPU has no invariant TSC, whose rate is
+    /// constant and synchronized between cores.
+    pub fn new() -> Option<Self> {
+        use std::arch::x86_64::__cpuid;
+
+        // CPUID.80000007H:EDX[8] indicates an invariant TSC
+        let max_extended_leaf = __cpuid(0x8000_0000).eax;
+        if max_extended_leaf < 0x8000_0007 || __cpuid(0x8000_0007).edx & (1 << 8) == 0 {
+            return None;
+        }
+        let start = Instant::now();
+        let start_ticks = rdtsc();
+        while start.elapsed() < Self::CALIBRATION_TIME {}
+        let ticks = rdtsc() - start_ticks;
+        let elapsed = start.elapsed();
+        Some(Self {
+            ticks_per_ns: ticks as f64 / elapsed.as_nanos() as f64,
+        })
+    }
+
+    /// The calibrated rate of the TSC, which can be used to convert cycles to nanoseconds.
+    pub fn ticks_per_ns(&self) -> f64 {
+        self.ticks_per_ns
+    }
+}
+
+#[cfg(target_arch = "x86_64")]
+#[inline(always)]
+fn rdtsc() -> u64 {
+    use std::arch::x86_64::{_mm_lfence, _rdtsc};
+
+    // the fences prevent reordering of the benchmarked code around the read of the TSC
+    // Safety: lfence and rdtsc are available on all x86_64 CPUs
+    unsafe {
+        _mm_lfence();
+        let ticks = _rdtsc();
+        _mm_lfence();
+        ticks
+    }
+}
+
+#[cfg(target_arch = "x86_64")]
+impl Timer for Tsc {
+    #[inline(always)]
+    fn read(&self) -> u64 {
+        rdtsc()
+    }
+
+    fn unit(&self) -> Unit {
+        Unit::Cycles
+    }
+}
+
+/// The CPU time consumed by the current thread (`CLOCK_THREAD_CPUTIME_ID`).
+///
+/// Time the thread spends sleeping or waiting, e.g. for I/O, is not measured.
+#[cfg(unix)]
+#[derive(Debug, Default)]
+pub struct ThreadCpuTime;
+
+#[cfg(unix)]
+impl Timer for ThreadCpuTime {
+    #[inline(always)]
+    fn read(&self) -> u64 {
+        clock_nanos(libc::CLOCK_THREAD_CPUTIME_ID)
+    }
+
+    fn unit(&self) -> Unit {
+        Unit::Nanoseconds
+    }
+}
+
+/// The CPU time consumed by all threads of the process (`CLOCK_PROCESS_CPUTIME_ID`).
+#[cfg(unix)]
+#[derive(Debug, Default)]
+pub struct ProcessCpuTime;
+
+#[cfg(unix)]
+impl Timer for ProcessCpuTime {
+    #[inline(always)]
+    fn read(&self) -> u64 {
+        clock_nanos(libc::CLOCK_PROCESS_CPUTIME_ID)
+    }
+
+    fn unit(&self) -> Unit {
+        Unit::Nanoseconds
+    }
+}
+
+#[cfg(unix)]
+#[inline(always)]
+fn clock_nanos(clock: libc::clockid_t) -> u64 {
+    let mut ts = libc::timespec {
+        tv_sec: 0,
+        tv_nsec: 0,
+    };
+    // Safety: ts is a valid timespec to write to
+    let res = unsafe { libc::clock_gettime(clock, &mut ts) };
+    debug_assert_eq!(res, 0, "clock_gettime failed");
+    ts.tv_sec as u64 * 1_000_000_000 + ts.tv_nsec as u64
+}
+
+#[cfg(test)]
+mod tests {
+    use std::hint::black_box;
+
+    use super::{Calibration, Timer, WallTime};
+
+    fn assert_measures_busy_loop(timer: &dyn Timer) {
+        let start = timer.read();
+        let mut x = 0_u64;
+        for i in 0..1_000_000 {
+            x = black_box(x.wrapping_add(i));
+        }
+        let end = timer.read();
+        assert!(end > start, "{timer:?}");
+    }
+
+    // miri supports neither the inline assembly of the TSC nor CPU time clocks
+    #[test]
+    #[cfg_attr(miri, ignore)]
+    fn test_timers() {
+        assert_measures_busy_loop(&WallTime::new());
+        #[cfg(unix)]
+        assert_measures_busy_loop(&super::ThreadCpuTime);
+        #[cfg(unix)]
+        assert_measures_busy_loop(&super::ProcessCpuTime);
+        #[cfg(target_arch = "x86_64")]
+        if let Some(tsc) = super::Tsc::new() {
+            assert_measures_busy_loop(&tsc);
+        }
+    }
+
+    #[test]
+    #[cfg_attr(miri, ignore)]
+    fn test_calibration() {
+        let calibration = Calibration::measure(&WallTime::new());
+        assert!(calibration.resolution > 0);
+        assert!(calibration.resolution < 1_000_000);
+        assert!(calibration.overhead > 0.0);
+    }
+}